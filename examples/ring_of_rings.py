@@ -0,0 +1,34 @@
+"""This example shows one parent ring waiting on several child rings.
+
+Each child ring does its own I/O. The parent polls the child ring fds, so a single
+blocking `wait()` on the parent wakes up as soon as any child has completions.
+"""
+
+from rusty_ring import Ring
+
+
+def main() -> None:
+    """Runs a few nops on each of three child rings, reaping them via the parent."""
+    with Ring(8) as parent, Ring(8) as a, Ring(8) as b, Ring(8) as c:
+        children = [a, b, c]
+        for index, child in enumerate(children):
+            # Ring fds only support one-shot polls: re-armed below once drained.
+            parent.prep_poll_ring(index, child)
+            for user_data in range(3):
+                child.prep_nop(user_data)
+            child.submit()
+        parent.submit()
+
+        remaining = 3 * len(children)
+        while remaining:
+            index = parent.wait().user_data
+            child = children[index]
+            while (event := child.peek()) is not None:
+                print(f"child {index}: op {event.user_data} -> {event.res}")
+                remaining -= 1
+            parent.prep_poll_ring(index, child)
+            parent.submit()
+
+
+if __name__ == "__main__":
+    main()
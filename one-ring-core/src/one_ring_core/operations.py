@@ -109,11 +109,12 @@ class FileOpen(IOOperation[FileOpenResult]):
     @override
     def prep(self, user_data: WorkerOperationID, ring: Ring) -> None:
         """Prepares a submission queue entry for the SQ."""
+        flags = self._mode_to_flags(self.mode)
         ring.prep_openat(
             user_data,
             self.path,
-            self._mode_to_flags(self.mode),
-            FileMode.RW_OWNER,
+            flags,
+            FileMode.RW_OWNER if flags & OpenFlags.CREAT else 0,
             AtFlags.FDCWD,
         )
 
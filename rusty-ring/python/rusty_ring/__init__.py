@@ -5,17 +5,88 @@ from rusty_ring._rusty_ring import (
     AT_EMPTY_PATH,
     AT_FDCWD,
     AT_SYMLINK_NOFOLLOW,
+    IOPRIO_CLASS_BE,
+    IOPRIO_CLASS_IDLE,
+    IOPRIO_CLASS_NONE,
+    IOPRIO_CLASS_RT,
+    IORING_ASYNC_CANCEL_ALL,
+    IORING_ASYNC_CANCEL_ANY,
+    IORING_ASYNC_CANCEL_FD,
+    IORING_ASYNC_CANCEL_OP,
+    IORING_ASYNC_CANCEL_USERDATA,
+    IORING_OP_ACCEPT,
+    IORING_OP_ASYNC_CANCEL,
+    IORING_OP_BIND,
+    IORING_OP_CLOSE,
+    IORING_OP_CONNECT,
+    IORING_OP_LISTEN,
+    IORING_OP_NOP,
+    IORING_OP_OPENAT,
+    IORING_OP_POLL_ADD,
+    IORING_OP_READ,
+    IORING_OP_RECV,
+    IORING_OP_SEND,
+    IORING_OP_SOCKET,
+    IORING_OP_STATX,
+    IORING_OP_TIMEOUT,
+    IORING_OP_URING_CMD,
+    IORING_OP_WRITE,
+    IORING_TIMEOUT_ABS,
+    IORING_TIMEOUT_BOOTTIME,
+    IORING_TIMEOUT_ETIME_SUCCESS,
+    IORING_TIMEOUT_REALTIME,
     IPPROTO_TCP,
+    MADV_DONTNEED,
+    MADV_NORMAL,
+    MADV_RANDOM,
+    MADV_SEQUENTIAL,
+    MADV_WILLNEED,
+    MAX_USER_DATA,
+    MSG_CTRUNC,
     MSG_DONTWAIT,
+    MSG_MORE,
     MSG_NOSIGNAL,
+    MSG_PEEK,
+    MSG_TRUNC,
+    MSG_WAITALL,
     O_APPEND,
     O_CLOEXEC,
     O_CREAT,
+    O_DIRECT,
+    O_DIRECTORY,
+    O_DSYNC,
+    O_EXCL,
+    O_NOFOLLOW,
     O_NONBLOCK,
     O_RDONLY,
     O_RDWR,
+    O_SYNC,
+    O_TMPFILE,
     O_TRUNC,
     O_WRONLY,
+    POLLERR,
+    POLLHUP,
+    POLLIN,
+    POLLOUT,
+    POLLPRI,
+    POLLRDHUP,
+    POSIX_FADV_DONTNEED,
+    POSIX_FADV_NOREUSE,
+    POSIX_FADV_NORMAL,
+    POSIX_FADV_RANDOM,
+    POSIX_FADV_SEQUENTIAL,
+    POSIX_FADV_WILLNEED,
+    RWF_APPEND,
+    RWF_DSYNC,
+    RWF_HIPRI,
+    RWF_NOWAIT,
+    RWF_SYNC,
+    RWH_WRITE_LIFE_EXTREME,
+    RWH_WRITE_LIFE_LONG,
+    RWH_WRITE_LIFE_MEDIUM,
+    RWH_WRITE_LIFE_NONE,
+    RWH_WRITE_LIFE_NOT_SET,
+    RWH_WRITE_LIFE_SHORT,
     S_IFDIR,
     S_IFIFO,
     S_IFLNK,
@@ -43,6 +114,10 @@ from rusty_ring._rusty_ring import (
     SOCK_DGRAM,
     SOCK_NONBLOCK,
     SOCK_STREAM,
+    SOCKET_URING_OP_GETSOCKOPT,
+    SOCKET_URING_OP_SETSOCKOPT,
+    SOCKET_URING_OP_SIOCINQ,
+    SOCKET_URING_OP_SIOCOUTQ,
     SOL_SOCKET,
     STATX_ALL,
     STATX_ATIME,
@@ -52,12 +127,33 @@ from rusty_ring._rusty_ring import (
     STATX_MTIME,
     STATX_SIZE,
     STATX_TYPE,
+    SYNC_FILE_RANGE_WAIT_AFTER,
+    SYNC_FILE_RANGE_WAIT_BEFORE,
+    SYNC_FILE_RANGE_WRITE,
     TCP_NODELAY,
+    WAKE_USER_DATA,
+    XATTR_CREATE,
+    XATTR_REPLACE,
+    BufferPool,
+    Chain,
     CompletionEvent,
+    CompletionQueueFull,
+    RecvMsgHeader,
     Ring,
+    RingCompletionError,
+    RingError,
+    RingNotInitialized,
+    RingParams,
+    RingSetupError,
     SockAddr,
+    SockErrorBuffer,
     StatxBuffer,
+    SubmissionQueueFull,
+    Waker,
+    ioprio_value,
+    is_supported,
 )
+from rusty_ring._sockets import socket_from_fd
 
 __all__ = [
     "AF_INET",
@@ -66,22 +162,97 @@ __all__ = [
     "AT_EMPTY_PATH",
     "AT_FDCWD",
     "AT_SYMLINK_NOFOLLOW",
+    "IOPRIO_CLASS_BE",
+    "IOPRIO_CLASS_IDLE",
+    "IOPRIO_CLASS_NONE",
+    "IOPRIO_CLASS_RT",
+    "IORING_ASYNC_CANCEL_ALL",
+    "IORING_ASYNC_CANCEL_ANY",
+    "IORING_ASYNC_CANCEL_FD",
+    "IORING_ASYNC_CANCEL_OP",
+    "IORING_ASYNC_CANCEL_USERDATA",
+    "IORING_OP_ACCEPT",
+    "IORING_OP_ASYNC_CANCEL",
+    "IORING_OP_BIND",
+    "IORING_OP_CLOSE",
+    "IORING_OP_CONNECT",
+    "IORING_OP_LISTEN",
+    "IORING_OP_NOP",
+    "IORING_OP_OPENAT",
+    "IORING_OP_POLL_ADD",
+    "IORING_OP_READ",
+    "IORING_OP_RECV",
+    "IORING_OP_SEND",
+    "IORING_OP_SOCKET",
+    "IORING_OP_STATX",
+    "IORING_OP_TIMEOUT",
+    "IORING_OP_URING_CMD",
+    "IORING_OP_WRITE",
+    "IORING_TIMEOUT_ABS",
+    "IORING_TIMEOUT_BOOTTIME",
+    "IORING_TIMEOUT_ETIME_SUCCESS",
+    "IORING_TIMEOUT_REALTIME",
     "IPPROTO_TCP",
+    "MADV_DONTNEED",
+    "MADV_NORMAL",
+    "MADV_RANDOM",
+    "MADV_SEQUENTIAL",
+    "MADV_WILLNEED",
+    "MAX_USER_DATA",
+    "MSG_CTRUNC",
     "MSG_DONTWAIT",
+    "MSG_MORE",
     "MSG_NOSIGNAL",
+    "MSG_PEEK",
+    "MSG_TRUNC",
+    "MSG_WAITALL",
     "O_APPEND",
     "O_CLOEXEC",
     "O_CREAT",
+    "O_DIRECT",
+    "O_DIRECTORY",
+    "O_DSYNC",
+    "O_EXCL",
+    "O_NOFOLLOW",
     "O_NONBLOCK",
     "O_RDONLY",
     "O_RDWR",
+    "O_SYNC",
+    "O_TMPFILE",
     "O_TRUNC",
     "O_WRONLY",
+    "POLLERR",
+    "POLLHUP",
+    "POLLIN",
+    "POLLOUT",
+    "POLLPRI",
+    "POLLRDHUP",
+    "POSIX_FADV_DONTNEED",
+    "POSIX_FADV_NOREUSE",
+    "POSIX_FADV_NORMAL",
+    "POSIX_FADV_RANDOM",
+    "POSIX_FADV_SEQUENTIAL",
+    "POSIX_FADV_WILLNEED",
+    "RWF_APPEND",
+    "RWF_DSYNC",
+    "RWF_HIPRI",
+    "RWF_NOWAIT",
+    "RWF_SYNC",
+    "RWH_WRITE_LIFE_EXTREME",
+    "RWH_WRITE_LIFE_LONG",
+    "RWH_WRITE_LIFE_MEDIUM",
+    "RWH_WRITE_LIFE_NONE",
+    "RWH_WRITE_LIFE_NOT_SET",
+    "RWH_WRITE_LIFE_SHORT",
     "SFD_CLOEXEC",
     "SFD_NONBLOCK",
     "SIGHUP",
     "SIGINT",
     "SIGTERM",
+    "SOCKET_URING_OP_GETSOCKOPT",
+    "SOCKET_URING_OP_SETSOCKOPT",
+    "SOCKET_URING_OP_SIOCINQ",
+    "SOCKET_URING_OP_SIOCOUTQ",
     "SOCK_CLOEXEC",
     "SOCK_DGRAM",
     "SOCK_NONBLOCK",
@@ -98,6 +269,9 @@ __all__ = [
     "STATX_MTIME",
     "STATX_SIZE",
     "STATX_TYPE",
+    "SYNC_FILE_RANGE_WAIT_AFTER",
+    "SYNC_FILE_RANGE_WAIT_BEFORE",
+    "SYNC_FILE_RANGE_WRITE",
     "S_IFDIR",
     "S_IFIFO",
     "S_IFLNK",
@@ -114,8 +288,25 @@ __all__ = [
     "S_IXOTH",
     "S_IXUSR",
     "TCP_NODELAY",
+    "WAKE_USER_DATA",
+    "XATTR_CREATE",
+    "XATTR_REPLACE",
+    "BufferPool",
+    "Chain",
     "CompletionEvent",
+    "CompletionQueueFull",
+    "RecvMsgHeader",
     "Ring",
+    "RingCompletionError",
+    "RingError",
+    "RingNotInitialized",
+    "RingParams",
+    "RingSetupError",
     "SockAddr",
+    "SockErrorBuffer",
     "StatxBuffer",
+    "SubmissionQueueFull",
+    "Waker",
+    "ioprio_value",
+    "is_supported",
 ]
@@ -1,6 +1,15 @@
+import socket
 import types
+from collections.abc import Buffer, Callable, Iterable
 from typing import Self
 
+class RingError(RuntimeError): ...
+class SubmissionQueueFull(RingError): ...
+class CompletionQueueFull(RingError): ...
+class RingNotInitialized(RingError): ...
+class RingCompletionError(RingError): ...
+class RingSetupError(OSError, RingError): ...
+
 class SockAddr:
     @staticmethod
     def v4(ip: str, port: int) -> SockAddr: ...
@@ -14,9 +23,42 @@ class CompletionEvent:
     def res(self) -> int: ...
     @property
     def flags(self) -> int: ...
+    @property
+    def completed_at_ns(self) -> int | None: ...
+    @property
+    def buffer_id(self) -> int | None: ...
+    @property
+    def buffer_ids(self) -> list[int] | None: ...
+    @property
+    def big_cqe(self) -> tuple[int, int] | None: ...
+    @property
+    def cookie(self) -> object: ...
+    @property
+    def more(self) -> bool: ...
+    @property
+    def bytes_transferred(self) -> int: ...
+    @property
+    def is_notification(self) -> bool: ...
+    @property
+    def is_eof(self) -> bool: ...
+    @property
+    def out_of_buffers(self) -> bool: ...
 
 class Ring:
-    def __init__(self, depth: int = 32) -> None: ...
+    def __init__(
+        self,
+        depth: int = 32,
+        *,
+        r_disabled: bool = False,
+        timestamps: bool = False,
+        sqpoll_idle_ms: int | None = None,
+        defer_taskrun: bool = False,
+        coop_taskrun: bool = False,
+        auto_submit: bool = False,
+        clamp: bool = False,
+        cqe32: bool = False,
+        sqe128: bool = False,
+    ) -> None: ...
     def __enter__(self) -> Self: ...
     def __exit__(
         self,
@@ -24,25 +66,237 @@ class Ring:
         exc_val: BaseException | None,
         exc_tb: types.TracebackType | None,
     ) -> bool: ...
+    async def __aenter__(self) -> Self: ...
+    async def __aexit__(
+        self,
+        exc_type: type[BaseException] | None,
+        exc_val: BaseException | None,
+        exc_tb: types.TracebackType | None,
+    ) -> bool: ...
+    def __len__(self) -> int: ...
+    def __bool__(self) -> bool: ...
+    @property
+    def depth(self) -> int: ...
+    @property
+    def is_open(self) -> bool: ...
+    @property
+    def close_on_exec(self) -> bool: ...
+    @property
+    def in_flight(self) -> int: ...
+    def reserve(self, additional: int) -> None: ...
+    @property
+    def sq_entries(self) -> int: ...
+    @property
+    def cq_entries(self) -> int: ...
+    def params(self) -> RingParams: ...
+    def register_restrictions(
+        self, allowed_opcodes: list[int], allowed_sqe_flags: int = 0
+    ) -> None: ...
+    def enable(self) -> None: ...
+    def register_personality(self) -> int: ...
+    def register_iowq_max_workers(
+        self, bounded: int, unbounded: int
+    ) -> tuple[int, int]: ...
+    def iowq_max_workers(self) -> tuple[int, int]: ...
+    def register_iowq_aff(self, cpus: Iterable[int]) -> None: ...
+    def unregister_iowq_aff(self) -> None: ...
+    def register_eventfd(self) -> int: ...
+    def fileno(self) -> int: ...
+    def register_buf_ring(self, group_id: int, entries: int, buf_size: int) -> None: ...
+    def read_buffer(self, group_id: int, buffer_id: int, nbytes: int) -> bytes: ...
+    def recycle_buffer(self, group_id: int, buffer_id: int) -> None: ...
+    def register_buffer_pool(self, count: int, buf_size: int) -> BufferPool: ...
+    def register_files_sparse(self, count: int) -> None: ...
+    def register_files_update(self, offset: int, fds: list[int]) -> int: ...
     def submit(self) -> int: ...
-    def peek(self) -> CompletionEvent | None: ...
+    def try_submit(self) -> tuple[int, int]: ...
+    def submit_nonblocking(self) -> int: ...
+    def cq_overflow(self) -> tuple[int, bool]: ...
+    def sq_pending(self) -> int: ...
+    def get_events(self) -> int: ...
+    def completion_view(self, max: int | None = None) -> list[tuple[int, int, int]]: ...
+    def advance_completions(self, n: int) -> int: ...
+    def reconcile_completions(self) -> int: ...
+    def completions_into(self, out: Buffer) -> int: ...
+    def peek(self, *, sync: bool = True) -> CompletionEvent | None: ...
     def wait(self) -> CompletionEvent: ...
-    def prep_nop(self, user_data: int) -> None: ...
-    def prep_timeout(self, user_data: int, sec: int, nsec: int) -> None: ...
-    def prep_close(
+    def set_default_wait_timeout(self, sec: int | None, nsec: int = 0) -> None: ...
+    def wait_and_drain(self) -> list[CompletionEvent]: ...
+    def submit_wait_drain(self, min_complete: int = 1) -> list[CompletionEvent]: ...
+    def for_each_completion(
+        self, callback: Callable[[int, int, int], object]
+    ) -> int: ...
+    def wait_until(
+        self, count: int, deadline_sec: int, deadline_nsec: int, *, clock: int = ...
+    ) -> list[CompletionEvent]: ...
+    async def wait_async(self) -> list[CompletionEvent]: ...
+    def wait_for(
+        self, user_data: int, timeout: float | None = None
+    ) -> CompletionEvent | None: ...
+    def shutdown(
+        self, timeout: float | None = None, *, cancel: bool = False
+    ) -> list[CompletionEvent]: ...
+    def unsafe_push_raw_sqe(self, sqe: bytes) -> None: ...
+    def chain(self) -> Chain: ...
+    def waker(self) -> Waker: ...
+    def prep_nop(self, user_data: int, *, cookie: object = None) -> None: ...
+    def prep_timeout(
+        self,
+        user_data: int,
+        sec: int,
+        nsec: int,
+        *,
+        clock: int = ...,
+        absolute: bool = False,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_timeouts(self, specs: list[tuple[int, int, int, int]]) -> None: ...
+    def prep_poll_add(
+        self,
+        user_data: int,
+        fd: int,
+        mask: int,
+        *,
+        multishot: bool = False,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_poll_ring(
+        self, user_data: int, ring: Ring, *, cookie: object = None
+    ) -> None: ...
+    def prep_poll_update(
+        self,
+        user_data: int,
+        target_user_data: int,
+        new_mask: int | None = None,
+        *,
+        new_user_data: int | None = None,
+        multishot: bool = False,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_uring_cmd(
         self,
         user_data: int,
         fd: int,
+        cmd_op: int,
+        cmd_buf: bytes = b"",
+        *,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_pidfd_wait(
+        self, user_data: int, pidfd: int, *, cookie: object = None
+    ) -> None: ...
+    def prep_fadvise(
+        self,
+        user_data: int,
+        fd: int,
+        offset: int,
+        length: int,
+        advice: int,
+        *,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_fsync(
+        self, user_data: int, fd: int, *, datasync: bool = False, cookie: object = None
+    ) -> None: ...
+    def prep_sync_file_range(
+        self,
+        user_data: int,
+        fd: int,
+        offset: int = 0,
+        nbytes: int = 0,
+        flags: int = 0,
+        *,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_madvise(
+        self,
+        user_data: int,
+        buf: Buffer,
+        advice: int,
+        offset: int = 0,
+        length: int | None = None,
+        *,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_close(
+        self,
+        user_data: int,
+        fd: int | None = None,
+        *,
+        file_index: int | None = None,
+        cookie: object = None,
     ) -> None: ...
     def prep_cancel(
-        self, user_data: int, target_user_data: int, flags: int = 0
+        self,
+        user_data: int,
+        target_user_data: int,
+        flags: int = 0,
+        *,
+        fd: int | None = None,
+        op_code: int | None = None,
+        cookie: object = None,
     ) -> None: ...
     def prep_read(
-        self, user_data: int, fd: int, buf: bytearray, nbytes: int, offset: int
+        self,
+        user_data: int,
+        fd: int,
+        buf: bytearray,
+        nbytes: int,
+        offset: int,
+        *,
+        buf_offset: int = 0,
+        ioprio: int = 0,
+        rw_flags: int = 0,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_write(
+        self,
+        user_data: int,
+        fd: int,
+        buf: bytes,
+        offset: int,
+        *,
+        ioprio: int = 0,
+        rw_flags: int = 0,
+        write_hint: int | None = None,
+        append: bool = False,
+        cookie: object = None,
     ) -> None: ...
-    def prep_write(self, user_data: int, fd: int, buf: bytes, offset: int) -> None: ...
     def prep_openat(
-        self, user_data: int, path: str, flags: int, mode: int, dir_fd: int
+        self,
+        user_data: int,
+        path: str,
+        flags: int,
+        mode: int = 0,
+        dir_fd: int = ...,
+        *,
+        personality: int | None = None,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_read_multishot(
+        self, user_data: int, fd: int, group_id: int, *, cookie: object = None
+    ) -> None: ...
+    def prep_read_fixed(
+        self,
+        user_data: int,
+        fd: int,
+        buf_index: int,
+        nbytes: int,
+        offset: int,
+        *,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_write_fixed(
+        self,
+        user_data: int,
+        fd: int,
+        buf_index: int,
+        nbytes: int,
+        offset: int,
+        *,
+        buf_offset: int = 0,
+        write_hint: int | None = None,
+        cookie: object = None,
     ) -> None: ...
     def prep_statx(
         self,
@@ -52,6 +306,46 @@ class Ring:
         flags: int,
         mask: int,
         dir_fd: int,
+        *,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_getxattr(
+        self,
+        user_data: int,
+        path: str,
+        name: str,
+        value: bytearray,
+        *,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_setxattr(
+        self,
+        user_data: int,
+        path: str,
+        name: str,
+        value: bytes,
+        flags: int = 0,
+        *,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_fgetxattr(
+        self,
+        user_data: int,
+        fd: int,
+        name: str,
+        value: bytearray,
+        *,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_fsetxattr(
+        self,
+        user_data: int,
+        fd: int,
+        name: str,
+        value: bytes,
+        flags: int = 0,
+        *,
+        cookie: object = None,
     ) -> None: ...
     def prep_socket(
         self,
@@ -60,26 +354,192 @@ class Ring:
         sock_type: int,
         protocol: int = 0,
         flags: int = 0,
+        *,
+        cookie: object = None,
     ) -> None: ...
-    def prep_socket_setopt(
+    def prep_listener(
         self,
         user_data: int,
-        fd: int,
+        sock_addr: SockAddr,
+        backlog: int = 128,
+        *,
+        reuseaddr: bool = True,
+        cookie: object = None,
+    ) -> list[int]: ...
+    def prep_socket_setopt(
+        self, user_data: int, fd: int, *, cookie: object = None
     ) -> None: ...
     def prep_socket_bind(
-        self, user_data: int, fd: int, sock_addr: SockAddr
+        self, user_data: int, fd: int, sock_addr: SockAddr, *, cookie: object = None
+    ) -> None: ...
+    def prep_socket_listen(
+        self, user_data: int, fd: int, backlog: int, *, cookie: object = None
+    ) -> None: ...
+    def prep_socket_accept(
+        self, user_data: int, fd: int, flags: int = 0, *, cookie: object = None
     ) -> None: ...
-    def prep_socket_listen(self, user_data: int, fd: int, backlog: int) -> None: ...
-    def prep_socket_accept(self, user_data: int, fd: int) -> None: ...
     def prep_socket_recv(
-        self, user_data: int, fd: int, buf: bytearray, flags: int = 0
+        self,
+        user_data: int,
+        fd: int,
+        buf: bytearray | None = None,
+        flags: int = 0,
+        *,
+        poll_first: bool = False,
+        buf_group: int | None = None,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_socket_recv_timeout(
+        self,
+        user_data: int,
+        fd: int,
+        buf: bytearray,
+        sec: int,
+        nsec: int,
+        flags: int = 0,
+        *,
+        cookie: object = None,
+    ) -> tuple[int, int]: ...
+    def prep_socket_recv_multishot(
+        self,
+        user_data: int,
+        fd: int,
+        group_id: int,
+        flags: int = 0,
+        *,
+        bundle: bool = False,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_socket_send_bundle(
+        self,
+        user_data: int,
+        fd: int,
+        bufs: list[bytes],
+        flags: int = 0,
+        *,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_recvmsg(
+        self,
+        user_data: int,
+        fd: int,
+        bufs: list[bytearray],
+        header: RecvMsgHeader,
+        flags: int = 0,
+        *,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_sendmsg_zc(
+        self,
+        user_data: int,
+        fd: int,
+        bufs: list[bytes],
+        addr: SockAddr | None,
+        flags: int = 0,
+        *,
+        cookie: object = None,
     ) -> None: ...
     def prep_socket_send(
-        self, user_data: int, fd: int, buf: bytes, flags: int = 0
+        self,
+        user_data: int,
+        fd: int,
+        buf: bytes,
+        flags: int = 0,
+        *,
+        poll_first: bool = False,
+        cookie: object = None,
+    ) -> None: ...
+    def prep_socket_send_all(
+        self,
+        user_data: int,
+        fd: int,
+        buf: bytes,
+        flags: int = 0,
+        *,
+        cookie: object = None,
     ) -> None: ...
     def prep_socket_connect(
-        self, user_data: int, fd: int, sock_addr: SockAddr
+        self, user_data: int, fd: int, sock_addr: SockAddr, *, cookie: object = None
     ) -> None: ...
+    def prep_socket_connect_timeout(
+        self,
+        user_data: int,
+        fd: int,
+        sock_addr: SockAddr,
+        sec: int,
+        nsec: int,
+        *,
+        cookie: object = None,
+    ) -> tuple[int, int]: ...
+    def prep_socket_connect_checked(
+        self,
+        user_data: int,
+        fd: int,
+        sock_addr: SockAddr,
+        error: SockErrorBuffer,
+        *,
+        cookie: object = None,
+    ) -> tuple[int, int]: ...
+
+def is_supported() -> bool: ...
+def ioprio_value(ioprio_class: int, level: int) -> int: ...
+def socket_from_fd(fd: int) -> socket.socket: ...
+
+class RecvMsgHeader:
+    def __init__(self, control_len: int = 0) -> None: ...
+    @property
+    def address(self) -> tuple[str, int] | None: ...
+    @property
+    def control(self) -> bytes: ...
+    @property
+    def msg_flags(self) -> int: ...
+
+class RingParams:
+    @property
+    def sq_entries(self) -> int: ...
+    @property
+    def cq_entries(self) -> int: ...
+    @property
+    def flags(self) -> int: ...
+    @property
+    def features(self) -> int: ...
+    @property
+    def sq_thread_cpu(self) -> int: ...
+    @property
+    def sq_thread_idle(self) -> int: ...
+    @property
+    def sq_off(self) -> dict[str, int]: ...
+    @property
+    def cq_off(self) -> dict[str, int]: ...
+
+class Chain:
+    def __enter__(self) -> Ring: ...
+    def __exit__(
+        self,
+        exc_type: type[BaseException] | None,
+        exc_val: BaseException | None,
+        exc_tb: types.TracebackType | None,
+    ) -> bool: ...
+
+class Waker:
+    def wake(self) -> None: ...
+
+class BufferPool:
+    @property
+    def count(self) -> int: ...
+    @property
+    def buf_size(self) -> int: ...
+    @property
+    def available(self) -> int: ...
+    def acquire(self, user_data: int) -> int: ...
+    def release(self, user_data: int) -> bool: ...
+    def read(self, index: int, nbytes: int) -> bytes: ...
+    def write(self, index: int, data: bytes, offset: int = 0) -> None: ...
+
+class SockErrorBuffer:
+    def __init__(self) -> None: ...
+    @property
+    def error(self) -> int: ...
 
 class StatxBuffer:
     def __init__(self) -> None: ...
@@ -91,6 +551,32 @@ class StatxBuffer:
     def ino(self) -> int: ...
     @property
     def mode(self) -> int: ...
+    @property
+    def mask(self) -> int: ...
+    @property
+    def nlink(self) -> int: ...
+    @property
+    def uid(self) -> int: ...
+    @property
+    def gid(self) -> int: ...
+    @property
+    def blksize(self) -> int: ...
+    @property
+    def blocks(self) -> int: ...
+    @property
+    def attributes(self) -> int: ...
+    @property
+    def atime_ns(self) -> int: ...
+    @property
+    def btime_ns(self) -> int: ...
+    @property
+    def ctime_ns(self) -> int: ...
+    @property
+    def mtime_ns(self) -> int: ...
+    @property
+    def dev(self) -> int: ...
+    @property
+    def rdev(self) -> int: ...
 
 # File open flags
 O_RDONLY: int
@@ -101,6 +587,13 @@ O_TRUNC: int
 O_APPEND: int
 O_NONBLOCK: int
 O_CLOEXEC: int
+O_EXCL: int
+O_DIRECT: int
+O_DIRECTORY: int
+O_NOFOLLOW: int
+O_SYNC: int
+O_DSYNC: int
+O_TMPFILE: int
 
 # File mode bits (permissions)
 S_IRUSR: int
@@ -158,6 +651,79 @@ TCP_NODELAY: int
 # Socket: send/recv flags
 MSG_NOSIGNAL: int
 MSG_DONTWAIT: int
+MSG_WAITALL: int
+MSG_PEEK: int
+MSG_MORE: int
+MSG_TRUNC: int
+MSG_CTRUNC: int
+
+# user_data limits (Waker.wake, every prep)
+WAKE_USER_DATA: int
+MAX_USER_DATA: int
+
+# Cancel flags (prep_cancel)
+IORING_ASYNC_CANCEL_ALL: int
+IORING_ASYNC_CANCEL_FD: int
+IORING_ASYNC_CANCEL_ANY: int
+IORING_ASYNC_CANCEL_USERDATA: int
+IORING_ASYNC_CANCEL_OP: int
+
+# Timeout flags (prep_timeouts)
+IORING_TIMEOUT_ABS: int
+IORING_TIMEOUT_BOOTTIME: int
+IORING_TIMEOUT_REALTIME: int
+IORING_TIMEOUT_ETIME_SUCCESS: int
+
+# Poll events
+POLLIN: int
+POLLPRI: int
+POLLOUT: int
+POLLERR: int
+POLLHUP: int
+POLLRDHUP: int
+
+# File and memory advice (prep_fadvise, prep_madvise)
+POSIX_FADV_NORMAL: int
+POSIX_FADV_SEQUENTIAL: int
+POSIX_FADV_RANDOM: int
+POSIX_FADV_NOREUSE: int
+POSIX_FADV_WILLNEED: int
+POSIX_FADV_DONTNEED: int
+MADV_NORMAL: int
+MADV_RANDOM: int
+MADV_SEQUENTIAL: int
+MADV_WILLNEED: int
+MADV_DONTNEED: int
+
+# Sync flags (prep_sync_file_range)
+SYNC_FILE_RANGE_WAIT_BEFORE: int
+SYNC_FILE_RANGE_WRITE: int
+SYNC_FILE_RANGE_WAIT_AFTER: int
+
+# Xattr flags (prep_setxattr, prep_fsetxattr)
+XATTR_CREATE: int
+XATTR_REPLACE: int
+
+# Per-op read/write flags (rw_flags)
+RWF_HIPRI: int
+RWF_DSYNC: int
+RWF_SYNC: int
+RWF_NOWAIT: int
+RWF_APPEND: int
+
+# Write lifetime hints (write_hint)
+RWH_WRITE_LIFE_NOT_SET: int
+RWH_WRITE_LIFE_NONE: int
+RWH_WRITE_LIFE_SHORT: int
+RWH_WRITE_LIFE_MEDIUM: int
+RWH_WRITE_LIFE_LONG: int
+RWH_WRITE_LIFE_EXTREME: int
+
+# I/O priority classes (ioprio_value)
+IOPRIO_CLASS_NONE: int
+IOPRIO_CLASS_RT: int
+IOPRIO_CLASS_BE: int
+IOPRIO_CLASS_IDLE: int
 
 # Signals
 SIGINT: int
@@ -167,3 +733,28 @@ SIGHUP: int
 # Signalfd flags
 SFD_NONBLOCK: int
 SFD_CLOEXEC: int
+
+# Socket commands (prep_uring_cmd)
+SOCKET_URING_OP_SIOCINQ: int
+SOCKET_URING_OP_SIOCOUTQ: int
+SOCKET_URING_OP_GETSOCKOPT: int
+SOCKET_URING_OP_SETSOCKOPT: int
+
+# io_uring opcodes (for register_restrictions)
+IORING_OP_NOP: int
+IORING_OP_READ: int
+IORING_OP_WRITE: int
+IORING_OP_OPENAT: int
+IORING_OP_CLOSE: int
+IORING_OP_STATX: int
+IORING_OP_TIMEOUT: int
+IORING_OP_POLL_ADD: int
+IORING_OP_ASYNC_CANCEL: int
+IORING_OP_SOCKET: int
+IORING_OP_RECV: int
+IORING_OP_SEND: int
+IORING_OP_BIND: int
+IORING_OP_LISTEN: int
+IORING_OP_ACCEPT: int
+IORING_OP_CONNECT: int
+IORING_OP_URING_CMD: int
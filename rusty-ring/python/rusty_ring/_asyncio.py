@@ -0,0 +1,58 @@
+import asyncio
+import contextlib
+import os
+from typing import TYPE_CHECKING
+
+if TYPE_CHECKING:
+    from types import TracebackType
+
+    from rusty_ring import CompletionEvent, Ring
+
+
+async def wait_async(ring: "Ring") -> "list[CompletionEvent]":
+    """`Ring.wait_async`: submit, then await at least one completion.
+
+    The ring's eventfd is cleared before each drain, so a CQE posted after
+    the drain leaves it readable and the next await returns straight away.
+    """
+    eventfd = ring.register_eventfd()
+    loop = asyncio.get_running_loop()
+    while True:
+        _clear(eventfd)
+        # With DEFER_TASKRUN, CQEs are only posted once we ask for them.
+        ring.get_events()
+        events = ring.submit_wait_drain(0)
+        if events:
+            return events
+
+        readable = loop.create_future()
+        loop.add_reader(eventfd, _set_done, readable)
+        try:
+            await readable
+        finally:
+            loop.remove_reader(eventfd)
+
+
+async def aenter(ring: "Ring") -> "Ring":
+    """`Ring.__aenter__`: the sync `__enter__`, which doesn't block."""
+    return ring.__enter__()
+
+
+async def aexit(
+    ring: "Ring",
+    exc_type: type[BaseException] | None,
+    exc_val: BaseException | None,
+    exc_tb: "TracebackType | None",
+) -> bool:
+    """`Ring.__aexit__`: the sync `__exit__`, which doesn't block."""
+    return ring.__exit__(exc_type, exc_val, exc_tb)
+
+
+def _clear(eventfd: int) -> None:
+    with contextlib.suppress(BlockingIOError):
+        os.eventfd_read(eventfd)
+
+
+def _set_done(future: "asyncio.Future[None]") -> None:
+    if not future.done():
+        future.set_result(None)
@@ -0,0 +1,12 @@
+import socket
+
+
+def socket_from_fd(fd: int) -> socket.socket:
+    """Wrap a socket fd from a completion, e.g. `prep_socket_accept`'s `res`.
+
+    Family, type and protocol are read back from the fd. The returned socket
+    owns the fd: closing it (or letting it be collected) closes the fd.
+    """
+    if fd < 0:
+        raise ValueError(f"Not a file descriptor: {fd} (a failed op's -errno?)")
+    return socket.socket(fileno=fd)
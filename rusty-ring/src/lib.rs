@@ -1,15 +1,93 @@
-use io_uring::{IoUring, opcode, types};
-use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use io_uring::register::Restriction;
+use io_uring::{IoUring, Parameters, Submitter, cqueue, opcode, squeue, types};
+use pyo3::buffer::{Element, PyBuffer};
+use pyo3::exceptions::{
+    PyOSError, PyRuntimeError, PyRuntimeWarning, PyTimeoutError, PyUserWarning, PyValueError,
+};
 use pyo3::prelude::*;
-use pyo3::types::{PyByteArray, PyBytes};
-use std::collections::HashMap;
+use pyo3::sync::PyOnceLock;
+use pyo3::types::{PyByteArray, PyBytes, PyDict, PyType};
+use std::alloc::{Layout, alloc_zeroed, dealloc};
+use std::collections::{HashMap, VecDeque};
 use std::ffi::CString;
 use std::net::{Ipv4Addr, Ipv6Addr};
-use std::os::unix::io::RawFd;
+use std::os::unix::io::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pyo3::create_exception!(
+    rusty_ring,
+    RingError,
+    PyRuntimeError,
+    "Base class for errors raised by a Ring."
+);
+pyo3::create_exception!(
+    rusty_ring,
+    SubmissionQueueFull,
+    RingError,
+    "No free SQE slot. Submit (and drain completions) before prepping more."
+);
+pyo3::create_exception!(
+    rusty_ring,
+    CompletionQueueFull,
+    RingError,
+    "The CQ is full and completions haven't been drained. Reap them before submitting."
+);
+pyo3::create_exception!(
+    rusty_ring,
+    RingNotInitialized,
+    RingError,
+    "The ring is used outside its context manager."
+);
+pyo3::create_exception!(
+    rusty_ring,
+    RingCompletionError,
+    RingError,
+    "Waiting for or reaping completions failed."
+);
+
+/// `RingSetupError`, built with `type()` on first use: it's both an `OSError`
+/// and a `RingError`, and `create_exception!` takes a single base.
+static RING_SETUP_ERROR: PyOnceLock<Py<PyType>> = PyOnceLock::new();
+
+fn ring_setup_error_type(py: Python<'_>) -> PyResult<&Bound<'_, PyType>> {
+    RING_SETUP_ERROR
+        .get_or_try_init(py, || {
+            let namespace = PyDict::new(py);
+            namespace.set_item("__module__", "rusty_ring")?;
+            namespace.set_item(
+                "__doc__",
+                "io_uring_setup failed. `errno` tells why (e.g. EPERM, ENOMEM, ENOSYS).",
+            )?;
+            // OSError first, so its constructor sets `errno` and `strerror`.
+            let bases = (py.get_type::<PyOSError>(), py.get_type::<RingError>());
+            let ty = py
+                .get_type::<PyType>()
+                .call1(("RingSetupError", bases, namespace))?;
+            Ok(ty.cast_into::<PyType>()?.unbind())
+        })
+        .map(|ty| ty.bind(py))
+}
+
+/// `RingSetupError` for a failed `io_uring_setup`.
+fn ring_setup_error(e: std::io::Error) -> PyErr {
+    Python::attach(|py| match ring_setup_error_type(py) {
+        Ok(ty) => PyErr::from_type(
+            ty.clone(),
+            (
+                e.raw_os_error().unwrap_or(0),
+                format!("io_uring_setup failed: {e}"),
+            ),
+        ),
+        Err(err) => err,
+    })
+}
 
 /// A completed io_uring operation.
 #[pyclass(frozen)]
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 struct CompletionEvent {
     #[pyo3(get)]
     user_data: u64,
@@ -17,10 +95,70 @@ struct CompletionEvent {
     res: i32,
     #[pyo3(get)]
     flags: u32,
+    /// `CLOCK_MONOTONIC` time the CQE was observed, if the ring records it.
+    #[pyo3(get)]
+    completed_at_ns: Option<u64>,
+    /// Provided buffers consumed by this completion, in fill order.
+    #[pyo3(get)]
+    buffer_ids: Option<Vec<u16>>,
+    /// The extra two words of a 32-byte CQE, on a `cqe32=True` ring.
+    #[pyo3(get)]
+    big_cqe: Option<(u64, u64)>,
+    /// The object passed as `cookie=` when the op was prepped.
+    #[pyo3(get)]
+    cookie: Option<Py<PyAny>>,
 }
 
 #[pymethods]
 impl CompletionEvent {
+    /// Id of the (first) provided buffer the kernel picked, if any.
+    #[getter]
+    fn buffer_id(&self) -> Option<u16> {
+        cqueue::buffer_select(self.flags)
+    }
+
+    /// Whether a multishot op stays armed and will post more CQEs
+    /// (`IORING_CQE_F_MORE`).
+    #[getter]
+    fn more(&self) -> bool {
+        cqueue::more(self.flags)
+    }
+
+    /// Whether this is the notification a zero-copy send posts once the
+    /// kernel no longer uses its buffers (`IORING_CQE_F_NOTIF`).
+    #[getter]
+    fn is_notification(&self) -> bool {
+        cqueue::notif(self.flags)
+    }
+
+    /// Bytes read or written: `res`, or 0 for a failed op.
+    #[getter]
+    fn bytes_transferred(&self) -> u32 {
+        self.res.max(0) as u32
+    }
+
+    /// True if an op selecting from a buffer group found it empty
+    /// (`res == -ENOBUFS`).
+    ///
+    /// Not fatal: recycle consumed buffers with `Ring.recycle_buffer` (or
+    /// register a bigger group), then prep the op again. A multishot op that
+    /// hits this has stopped, so it needs re-arming too.
+    #[getter]
+    fn out_of_buffers(&self) -> bool {
+        self.res == -libc::ENOBUFS
+    }
+
+    /// True for a read/recv that hit end of input: end of file, or the peer
+    /// shut down its side of a pipe or stream socket.
+    ///
+    /// That is exactly `res == 0`; a short positive count is not EOF and the
+    /// rest may be read with another op. Only meaningful for reads and recvs
+    /// of a non-zero length (a nop also completes with 0).
+    #[getter]
+    fn is_eof(&self) -> bool {
+        self.res == 0
+    }
+
     fn __repr__(&self) -> String {
         format!(
             "CompletionEvent(user_data={}, res={}, flags={})",
@@ -29,12 +167,404 @@ impl CompletionEvent {
     }
 }
 
+/// `io_uring_enter` flag to process completions without submitting.
+const IORING_ENTER_GETEVENTS: u32 = 1 << 0;
+
+/// Force an internal poll before the first recv/send attempt.
+const IORING_RECVSEND_POLL_FIRST: u16 = 1 << 0;
+
+/// `fcntl` command and values for a file's write lifetime hint; the libc
+/// crate doesn't define them.
+const F_SET_RW_HINT: libc::c_int = 1036;
+const RWH_WRITE_LIFE_NOT_SET: u64 = 0;
+const RWH_WRITE_LIFE_NONE: u64 = 1;
+const RWH_WRITE_LIFE_SHORT: u64 = 2;
+const RWH_WRITE_LIFE_MEDIUM: u64 = 3;
+const RWH_WRITE_LIFE_LONG: u64 = 4;
+const RWH_WRITE_LIFE_EXTREME: u64 = 5;
+
+/// Size of the direct descriptor table used by `prep_listener`, which bounds
+/// how many listener chains may be in flight at once.
+const LISTENER_FILE_SLOTS: u32 = 16;
+
+/// `PollRemove` flags (in `len`) turning it into a poll update.
+const IORING_POLL_ADD_MULTI: u32 = 1 << 0;
+const IORING_POLL_UPDATE_EVENTS: u32 = 1 << 1;
+const IORING_POLL_UPDATE_USER_DATA: u32 = 1 << 2;
+
+/// `AsyncCancel` flags, not all of which the io-uring crate's `CancelBuilder` covers.
+const IORING_ASYNC_CANCEL_ALL: u32 = 1 << 0;
+const IORING_ASYNC_CANCEL_FD: u32 = 1 << 1;
+const IORING_ASYNC_CANCEL_ANY: u32 = 1 << 2;
+const IORING_ASYNC_CANCEL_USERDATA: u32 = 1 << 4;
+const IORING_ASYNC_CANCEL_OP: u32 = 1 << 5;
+
+/// I/O priority classes; the class sits above the 13-bit level field.
+const IOPRIO_CLASS_SHIFT: u16 = 13;
+const IOPRIO_CLASS_NONE: u16 = 0;
+const IOPRIO_CLASS_RT: u16 = 1;
+const IOPRIO_CLASS_BE: u16 = 2;
+const IOPRIO_CLASS_IDLE: u16 = 3;
+
+/// Set on the user_data of a `LINK_TIMEOUT` pushed on behalf of an op.
+const LINK_TIMEOUT_TAG: u64 = 1 << 63;
+
+/// user_data of the cancel-everything SQE pushed by `shutdown(cancel=True)`.
+const SHUTDOWN_CANCEL_USER_DATA: u64 = u64::MAX;
+
+/// user_data of the deadline timeout pushed by `wait_until`.
+const WAIT_UNTIL_USER_DATA: u64 = u64::MAX - 1;
+
+/// user_data of the CQE a `Waker` posts. It isn't an op, so it's not counted
+/// in `in_flight`.
+const WAKE_USER_DATA: u64 = u64::MAX - 2;
+
+/// Set on the user_data of the `SO_ERROR` getsockopt linked after a connect.
+const SO_ERROR_TAG: u64 = 1 << 62;
+
+/// Largest user_data an op may take. The top two bits are the ring's own:
+/// the tags above, and the reserved values at the very top.
+const MAX_USER_DATA: u64 = SO_ERROR_TAG - 1;
+
+/// `IORING_OP_URING_CMD` sub-commands for sockets (`enum io_uring_socket_op`).
+const SOCKET_URING_OP_SIOCINQ: u32 = 0;
+const SOCKET_URING_OP_SIOCOUTQ: u32 = 1;
+const SOCKET_URING_OP_GETSOCKOPT: u32 = 2;
+const SOCKET_URING_OP_SETSOCKOPT: u32 = 3;
+
+/// Mirror of the kernel's 64-byte `struct io_uring_sqe`.
+///
+/// The io-uring crate doesn't expose every SQE field on every opcode builder,
+/// so `patch_sqe` lets us set the missing ones after `build()`.
+#[repr(C)]
+struct RawSqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    op_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    addr3: u64,
+    pad: u64,
+}
+
+/// Mirror of the kernel's `struct io_uring_params`, which `Parameters`
+/// wraps without exposing every field.
+#[repr(C)]
+struct RawParams {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: [u32; 10],
+    cq_off: [u32; 10],
+}
+
+/// Field names of `struct io_sqring_offsets`, by `u32` slot. The trailing
+/// `user_addr` is a `u64` over the last two slots.
+const SQ_OFF_FIELDS: [&str; 7] = [
+    "head",
+    "tail",
+    "ring_mask",
+    "ring_entries",
+    "flags",
+    "dropped",
+    "array",
+];
+
+/// Field names of `struct io_cqring_offsets`, likewise.
+const CQ_OFF_FIELDS: [&str; 7] = [
+    "head",
+    "tail",
+    "ring_mask",
+    "ring_entries",
+    "overflow",
+    "cqes",
+    "flags",
+];
+
+/// Apply `f` to the raw fields of a built SQE.
+fn patch_sqe(
+    entry: io_uring::squeue::Entry,
+    f: impl FnOnce(&mut RawSqe),
+) -> io_uring::squeue::Entry {
+    // SAFETY: `squeue::Entry` is a `#[repr(C)]` wrapper around `io_uring_sqe`,
+    // which has the same size and layout as `RawSqe`.
+    let mut raw: RawSqe = unsafe { std::mem::transmute(entry) };
+    f(&mut raw);
+    unsafe { std::mem::transmute(raw) }
+}
+
+/// Offsets by field name, as in `RingParams`.
+fn named_offsets(names: &[&'static str], offsets: &[u32]) -> HashMap<&'static str, u32> {
+    names.iter().copied().zip(offsets.iter().copied()).collect()
+}
+
+/// Where a `prep_socket_send_all` is in its (pinned) `bytes`.
+struct SendAllState {
+    addr: usize,
+    len: usize,
+    sent: usize,
+    fd: RawFd,
+    flags: i32,
+}
+
+/// A bytearray pinned on behalf of `ops` in-flight ops.
+struct SharedBuffer {
+    #[allow(dead_code)]
+    buf: Py<PyByteArray>,
+    ops: usize,
+}
+
 #[allow(dead_code)]
 struct StatxRequest {
     path: CString,
     statxbuf: Py<StatxBuffer>,
 }
 
+/// Path (`None` for the fd-based ops) and attribute name of an xattr op,
+/// and the bytearray (get) or bytes (set) holding the value.
+#[allow(dead_code)]
+struct XattrRequest {
+    path: Option<CString>,
+    name: CString,
+    value: Py<PyAny>,
+}
+
+#[allow(dead_code)]
+struct RecvMsgRequest {
+    bufs: Vec<Py<PyByteArray>>,
+    header: Py<RecvMsgHeader>,
+    /// The header's in-flight flag, cleared when the op's pins are released.
+    in_flight: Arc<AtomicBool>,
+}
+
+impl Drop for RecvMsgRequest {
+    fn drop(&mut self) {
+        self.in_flight.store(false, Ordering::Release);
+    }
+}
+
+/// The `msghdr` of a `prep_sendmsg_zc`, with the iovecs, buffers and
+/// address it points at.
+#[allow(dead_code)]
+struct SendMsgRequest {
+    msghdr: Box<libc::msghdr>,
+    iovecs: Vec<libc::iovec>,
+    bufs: Vec<Py<PyBytes>>,
+    addr: Option<Arc<SockAddrInner>>,
+}
+
+// SAFETY: the raw pointers in `msghdr` and `iovecs` only point into memory
+// owned by this struct.
+unsafe impl Send for SendMsgRequest {}
+unsafe impl Sync for SendMsgRequest {}
+
+/// What an in-flight op pinned for the kernel to read or write through.
+/// Boxed values keep a stable address across map resizes.
+#[allow(dead_code)]
+enum Pinned {
+    /// Key of the bytearray in `shared_mutable_buffers`.
+    MutableBuffer(usize),
+    ImmutableBuffer(Py<PyBytes>),
+    /// CString for a path passed to openat.
+    Path(CString),
+    Timespec(Box<types::Timespec>),
+    /// An address shared with the `SockAddr` it came from (and any other op
+    /// using it). The `Arc` keeps it at a stable address.
+    SockAddr(Arc<SockAddrInner>),
+    Sockopt(Box<i32>),
+    Statx(StatxRequest),
+    Xattr(XattrRequest),
+    /// Scatter buffers and header for recvmsg.
+    RecvMsg(RecvMsgRequest),
+    SendMsg(SendMsgRequest),
+    /// A buffer export (e.g. of an `mmap`) whose memory the op refers to.
+    BufferView(PyBuffer<u8>),
+    /// SO_ERROR destination for a checked connect.
+    SocketError(Py<SockErrorBuffer>),
+}
+
+/// An io_uring instance with 64- or 128-byte SQEs (`sqe128=True`), and 16-
+/// or 32-byte CQEs (`cqe32=True`).
+enum Uring {
+    Sqe64Cqe16(IoUring),
+    Sqe64Cqe32(IoUring<squeue::Entry, cqueue::Entry32>),
+    Sqe128Cqe16(IoUring<squeue::Entry128, cqueue::Entry>),
+    Sqe128Cqe32(IoUring<squeue::Entry128, cqueue::Entry32>),
+}
+
+/// Evaluate `$body` with `$ring` bound to whichever `IoUring` `$uring` holds.
+macro_rules! with_uring {
+    ($uring:expr, $ring:ident => $body:expr) => {
+        match $uring {
+            Uring::Sqe64Cqe16($ring) => $body,
+            Uring::Sqe64Cqe32($ring) => $body,
+            Uring::Sqe128Cqe16($ring) => $body,
+            Uring::Sqe128Cqe32($ring) => $body,
+        }
+    };
+}
+
+/// A reaped CQE, with the extra 16 bytes of a 32-byte one.
+type Cqe = (cqueue::Entry, Option<[u64; 2]>);
+
+/// A CQE of either size, as reaped.
+trait IntoCqe: cqueue::EntryMarker {
+    fn into_cqe(self) -> Cqe;
+}
+
+impl IntoCqe for cqueue::Entry {
+    fn into_cqe(self) -> Cqe {
+        (self, None)
+    }
+}
+
+impl IntoCqe for cqueue::Entry32 {
+    fn into_cqe(self) -> Cqe {
+        let big_cqe = *self.big_cqe();
+        (self.into(), Some(big_cqe))
+    }
+}
+
+/// `entries` zero-padded to 128 bytes each.
+fn widen(entries: &[squeue::Entry]) -> Vec<squeue::Entry128> {
+    entries.iter().cloned().map(Into::into).collect()
+}
+
+/// user_data of a 128-byte SQE.
+fn wide_user_data(entry: &squeue::Entry128) -> u64 {
+    // SAFETY: `Entry128` is `#[repr(C)]`, with the 64-byte `Entry` first.
+    unsafe { &*(entry as *const squeue::Entry128).cast::<squeue::Entry>() }.get_user_data()
+}
+
+impl Uring {
+    fn submitter(&self) -> Submitter<'_> {
+        with_uring!(self, ring => ring.submitter())
+    }
+
+    fn params(&self) -> &Parameters {
+        with_uring!(self, ring => ring.params())
+    }
+
+    fn submit(&self) -> std::io::Result<usize> {
+        with_uring!(self, ring => ring.submit())
+    }
+
+    fn submit_and_wait(&self, want: usize) -> std::io::Result<usize> {
+        with_uring!(self, ring => ring.submit_and_wait(want))
+    }
+
+    /// Push `entries` onto the SQ, all or none.
+    ///
+    /// # Safety
+    ///
+    /// As for `SubmissionQueue::push_multiple`.
+    unsafe fn push_multiple(&mut self, entries: &[squeue::Entry]) -> Result<(), squeue::PushError> {
+        match self {
+            Uring::Sqe64Cqe16(ring) => unsafe { ring.submission().push_multiple(entries) },
+            Uring::Sqe64Cqe32(ring) => unsafe { ring.submission().push_multiple(entries) },
+            // The SQEs are zero-padded to 128 bytes.
+            Uring::Sqe128Cqe16(ring) => unsafe { ring.submission().push_multiple(&widen(entries)) },
+            Uring::Sqe128Cqe32(ring) => unsafe { ring.submission().push_multiple(&widen(entries)) },
+        }
+    }
+
+    /// Push 128-byte `entries` onto the SQ, all or none.
+    ///
+    /// # Safety
+    ///
+    /// As for `SubmissionQueue::push_multiple`.
+    ///
+    /// # Panics
+    ///
+    /// If the ring has 64-byte SQEs.
+    unsafe fn push_wide(&mut self, entries: &[squeue::Entry128]) -> Result<(), squeue::PushError> {
+        match self {
+            Uring::Sqe128Cqe16(ring) => unsafe { ring.submission().push_multiple(entries) },
+            Uring::Sqe128Cqe32(ring) => unsafe { ring.submission().push_multiple(entries) },
+            Uring::Sqe64Cqe16(_) | Uring::Sqe64Cqe32(_) => {
+                unreachable!("128-byte SQE pushed to a ring with 64-byte SQEs")
+            }
+        }
+    }
+
+    /// Free SQ slots, without syncing with the kernel.
+    fn sq_space(&self) -> usize {
+        // SAFETY: `&self` rules out a concurrent `submission()`.
+        with_uring!(self, ring => {
+            let sq = unsafe { ring.submission_shared() };
+            sq.capacity() - sq.len()
+        })
+    }
+
+    /// SQEs not yet consumed by the kernel, and whether the SQPOLL thread
+    /// needs waking to consume them.
+    fn sq_pending(&mut self) -> (usize, bool) {
+        with_uring!(self, ring => {
+            let mut sq = ring.submission();
+            sq.sync();
+            (sq.len(), sq.need_wakeup())
+        })
+    }
+
+    /// CQEs the kernel dropped for lack of CQ room, and whether it's
+    /// holding back completions until there is room (`IORING_SQ_CQ_OVERFLOW`).
+    fn cq_overflow(&mut self) -> (u32, bool) {
+        with_uring!(self, ring => {
+            let dropped = ring.completion().overflow();
+            (dropped, ring.submission().cq_overflow())
+        })
+    }
+
+    /// Whether task work is waiting to post completions (`IORING_SQ_TASKRUN`,
+    /// only set with `IORING_SETUP_TASKRUN_FLAG`).
+    fn taskrun(&mut self) -> bool {
+        with_uring!(self, ring => ring.submission().taskrun())
+    }
+
+    /// CQEs ready to reap.
+    fn cq_len(&mut self) -> usize {
+        with_uring!(self, ring => ring.completion().len())
+    }
+
+    /// Up to `max` ready CQEs, oldest first. They're only consumed if
+    /// `consume`; otherwise the next call sees them again.
+    fn completions(&mut self, max: usize, consume: bool) -> Vec<Cqe> {
+        with_uring!(self, ring => take_cqes(ring.completion(), max, consume))
+    }
+}
+
+impl AsRawFd for Uring {
+    fn as_raw_fd(&self) -> RawFd {
+        with_uring!(self, ring => ring.as_raw_fd())
+    }
+}
+
+/// Take up to `max` CQEs off `cq`, publishing the new head only if `consume`.
+fn take_cqes<C: IntoCqe>(
+    mut cq: cqueue::CompletionQueue<'_, C>,
+    max: usize,
+    consume: bool,
+) -> Vec<Cqe> {
+    let cqes = cq.by_ref().take(max).map(C::into_cqe).collect();
+    if !consume {
+        // Dropping the queue would publish its advanced head.
+        std::mem::forget(cq);
+    }
+    cqes
+}
+
 /// Owns an io_uring instance and exposes prep/submit/complete operations.
 ///
 /// Usage from Python:
@@ -45,451 +575,3847 @@ struct StatxRequest {
 ///         cqe = ring.wait()
 /// ```
 ///
+/// An op's `user_data` is any value up to `MAX_USER_DATA`. The top two bits
+/// are reserved for the ring's own ops (such as link timeouts and
+/// `WAKE_USER_DATA`), so a prep given a larger one raises `ValueError`.
 #[pyclass]
 struct Ring {
-    ring: Option<IoUring>,
+    ring: Option<Uring>,
     depth: u32,
 
-    /// Buffers that are currently owned by the kernel (between submit and CQE).
-    /// Keyed by `user_data` so they can be released when the CQE arrives.
+    /// Create the ring with `IORING_SETUP_R_DISABLED`.
+    r_disabled: bool,
+
+    /// Create the ring with `IORING_SETUP_SQPOLL`, with this poller idle time.
+    sqpoll_idle_ms: Option<u32>,
+
+    /// Stamp each `CompletionEvent` with the time it was observed.
+    timestamps: bool,
+
+    /// Create the ring with `IORING_SETUP_SINGLE_ISSUER | IORING_SETUP_DEFER_TASKRUN`.
+    defer_taskrun: bool,
+
+    /// Create the ring with `IORING_SETUP_COOP_TASKRUN | IORING_SETUP_TASKRUN_FLAG`.
+    coop_taskrun: bool,
+
+    /// Create the ring with `IORING_SETUP_CLAMP`, capping an oversized depth.
+    clamp: bool,
+
+    /// Create the ring with `IORING_SETUP_CQE32`, for ops posting 32-byte
+    /// CQEs; their extra data is `CompletionEvent.big_cqe`.
+    cqe32: bool,
+
+    /// Create the ring with `IORING_SETUP_SQE128`, for ops taking 128-byte
+    /// SQEs (see `unsafe_push_raw_sqe`).
+    sqe128: bool,
+
+    /// Submit queued SQEs instead of raising `SubmissionQueueFull` on a full SQ.
+    /// Completions still need reaping: a prep can then raise
+    /// `CompletionQueueFull` instead.
+    auto_submit: bool,
+
+    /// What each in-flight op pinned, keyed by `user_data`, so it can be
+    /// released when the CQE arrives.
     ///
-    /// The kernel holds raw pointers into these buffers. They must be kept
-    /// alive and un-resized until the corresponding CQE is consumed.
-    /// TODO: Consolidate into 1.
-    pinned_mutable_buffers: HashMap<u64, Py<PyByteArray>>,
+    /// The kernel holds raw pointers into these. They must be kept alive
+    /// and un-resized until the corresponding CQE is consumed.
+    pinned: HashMap<u64, Pinned>,
 
-    pinned_immutable_buffers: HashMap<u64, Py<PyBytes>>,
+    /// The bytearrays behind `Pinned::MutableBuffer`, keyed by object
+    /// address, pinned once however many ops write into them.
+    shared_mutable_buffers: HashMap<usize, SharedBuffer>,
 
-    /// CStrings for paths passed to openat.
-    pinned_paths: HashMap<u64, CString>,
+    /// Buffer group each buffer-selecting op draws from, and whether it's a
+    /// bundle (whose CQEs may fill several buffers), keyed by `user_data`.
+    buffer_groups: HashMap<u64, (u16, bool)>,
 
-    /// Timespecs for timeouts.
-    pinned_timespecs: HashMap<u64, types::Timespec>,
+    /// Objects passed as `cookie=`, handed back on the op's completions.
+    cookies: HashMap<u64, Py<PyAny>>,
 
-    /// Addresses for sockets.
-    pinned_sockaddr: HashMap<u64, SockAddrInner>,
+    /// Registered provided-buffer rings, keyed by buffer group id.
+    buf_rings: HashMap<u16, BufRing>,
 
-    /// Socket option values. Boxed for pointer stability across HashMap resizes.
-    pinned_sockopts: HashMap<u64, Box<i32>>,
+    /// Free slots in the direct descriptor table `prep_listener` builds its
+    /// sockets in, or `None` until the table is registered on first use.
+    listener_slots_free: Option<Vec<u32>>,
 
-    // Statx buffers
-    pinned_statx_buffers: HashMap<u64, StatxRequest>,
-}
+    /// Direct descriptor slot of each `prep_listener` chain, keyed by the
+    /// user_data of its last op.
+    listener_slots: HashMap<u64, u32>,
 
-impl Ring {
-    fn uring_mut(&mut self) -> PyResult<&mut IoUring> {
-        self.ring
-            .as_mut()
-            .ok_or_else(|| PyRuntimeError::new_err("Ring not initialised (use as context manager)"))
-    }
+    /// Slot count of the file table registered with `register_files_sparse`.
+    file_table_len: Option<u32>,
 
-    /// Push an entry onto the SQ. Panics if SQ is full.
-    fn push_entry(&mut self, entry: io_uring::squeue::Entry) -> PyResult<()> {
-        let ring = self.uring_mut()?;
-        // SAFETY: we trust that the caller has set up the entry correctly and
-        // that any buffers referenced are pinned in `pinned_buffers`.
-        unsafe {
-            ring.submission()
-                .push(&entry)
-                .map_err(|_| PyRuntimeError::new_err("Submission queue is full"))?;
-        }
-        Ok(())
-    }
+    /// Ops consumed by `advance_completions` whose pins are still held, until
+    /// `reconcile_completions`.
+    deferred_releases: Vec<u64>,
 
-    /// Release any pinned resources associated with a completed user_data.
-    fn release_pinned(&mut self, user_data: u64) {
-        self.pinned_mutable_buffers.remove(&user_data);
-        self.pinned_immutable_buffers.remove(&user_data);
-        self.pinned_paths.remove(&user_data);
-        self.pinned_sockaddr.remove(&user_data);
-        self.pinned_timespecs.remove(&user_data);
-        self.pinned_sockopts.remove(&user_data);
-        self.pinned_statx_buffers.remove(&user_data);
-    }
+    /// Progress of `prep_socket_send_all` ops, keyed by `user_data`.
+    send_alls: HashMap<u64, SendAllState>,
 
-    fn cqe_to_event(&mut self, cqe: &io_uring::cqueue::Entry) -> CompletionEvent {
-        let user_data = cqe.user_data();
-        self.release_pinned(user_data);
-        CompletionEvent {
-            user_data,
-            res: cqe.result(),
-            flags: cqe.flags(),
-        }
-    }
+    /// Buffer rings of in-flight bundled sends, keyed by `user_data`.
+    send_bundles: HashMap<u64, SendBundleRing>,
+
+    /// Completions reaped by `wait_for` on behalf of other ops, handed out
+    /// by later `peek`/`wait`/`wait_and_drain` calls before any new CQE.
+    completions: VecDeque<CompletionEvent>,
+
+    /// The eventfd from `register_eventfd`, signalled on every CQE.
+    eventfd: Option<OwnedFd>,
+
+    /// Registered (fixed) buffers, if `register_buffer_pool` was called.
+    buffer_pool: Option<Py<BufferPool>>,
+
+    /// Pushed SQEs whose final CQE hasn't been reaped yet. Reaped ones
+    /// waiting in `completions` are counted by `pending_ops`.
+    in_flight: u32,
+
+    /// Dropped CQE count last seen by `reap`, so each new drop warns once.
+    cq_dropped: u32,
+
+    /// How long `wait()` blocks before raising `TimeoutError`, or `None` to
+    /// block until a CQE arrives. Set with `set_default_wait_timeout`.
+    wait_timeout: Option<Duration>,
+
+    /// Set by `shutdown()`; refuses new preps.
+    shutting_down: bool,
+
+    /// SQEs prepped inside an open `chain()`, pushed together when it exits.
+    chain: Option<Vec<squeue::Entry>>,
 }
 
-#[pymethods]
 impl Ring {
-    #[new]
-    #[pyo3(signature = (depth = 32))]
-    fn new(depth: u32) -> Self {
-        Ring {
-            ring: None,
-            depth,
-            pinned_mutable_buffers: HashMap::new(),
-            pinned_immutable_buffers: HashMap::new(),
-            pinned_paths: HashMap::new(),
-            pinned_timespecs: HashMap::new(),
-            pinned_sockaddr: HashMap::new(),
-            pinned_sockopts: HashMap::new(),
-            pinned_statx_buffers: HashMap::new(),
-        }
+    fn uring(&self) -> PyResult<&Uring> {
+        self.ring.as_ref().ok_or_else(|| {
+            RingNotInitialized::new_err("Ring not initialised (use as context manager)")
+        })
     }
 
-    /// Python CM protocol.
-    fn __enter__(mut slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
-        let ring = IoUring::new(slf.depth)
-            .map_err(|e| PyRuntimeError::new_err(format!("io_uring_setup failed: {e}")))?;
-        slf.ring = Some(ring);
-        Ok(slf)
+    fn uring_mut(&mut self) -> PyResult<&mut Uring> {
+        self.ring.as_mut().ok_or_else(|| {
+            RingNotInitialized::new_err("Ring not initialised (use as context manager)")
+        })
     }
 
-    #[pyo3(signature = (_exc_type=None, _exc_val=None, _exc_tb=None))]
-    fn __exit__(
-        &mut self,
-        _exc_type: Option<&Bound<'_, PyAny>>,
-        _exc_val: Option<&Bound<'_, PyAny>>,
-        _exc_tb: Option<&Bound<'_, PyAny>>,
-    ) -> PyResult<bool> {
-        self.pinned_mutable_buffers.clear();
-        self.pinned_immutable_buffers.clear();
-        self.pinned_paths.clear();
-        self.pinned_sockaddr.clear();
-        self.pinned_timespecs.clear();
-        self.pinned_sockopts.clear();
-        self.pinned_statx_buffers.clear();
-        self.ring = None; // Drop triggers internal io_uring cleanup
-        Ok(false)
+    /// Push an entry onto the SQ. Raises if the SQ is full.
+    fn push_entry(&mut self, entry: io_uring::squeue::Entry) -> PyResult<()> {
+        self.push_entries(&[entry])
     }
 
-    /// Submit all queued SQEs to the kernel. Returns number submitted.
-    fn submit(&mut self) -> PyResult<u32> {
-        let n = self
-            .uring_mut()?
-            .submit()
-            .map_err(|e| PyRuntimeError::new_err(format!("io_uring_submit failed: {e}")))?;
-        Ok(n as u32)
+    /// Push `entries` onto the SQ, all or none. Inside a `chain()` they're
+    /// held back until it exits.
+    fn push_entries(&mut self, entries: &[squeue::Entry]) -> PyResult<()> {
+        for entry in entries {
+            check_user_data(entry.get_user_data())?;
+        }
+        self.push_own_entries(entries)
     }
 
-    /// Non-blocking peek.
-    fn peek(&mut self) -> PyResult<Option<CompletionEvent>> {
-        let ring = self.uring_mut()?;
-        let cq = ring.completion();
-        let cqe = cq.into_iter().next();
-        match cqe {
-            Some(cqe) => Ok(Some(self.cqe_to_event(&cqe))),
-            None => Ok(None),
+    /// `push_entries` without the user_data check, for the ring's own entries
+    /// with reserved user_data and for raw SQEs.
+    fn push_own_entries(&mut self, entries: &[squeue::Entry]) -> PyResult<()> {
+        if self.shutting_down {
+            return Err(RingError::new_err("Ring is shutting down"));
+        }
+        if let Some(chain) = self.chain.as_mut() {
+            chain.extend_from_slice(entries);
+            return Ok(());
         }
+        self.push_entries_unchecked(entries)
     }
 
-    /// Blocking wait for at least one CQE and return it.
-    fn wait(&mut self, py: Python<'_>) -> PyResult<CompletionEvent> {
-        let ring = self.uring_mut()?;
-        py.detach(|| ring.submit_and_wait(1))
-            .map_err(|e| PyRuntimeError::new_err(format!("io_uring_wait failed: {e}")))?;
-        let cqe = ring
-            .completion()
-            .next()
-            .ok_or_else(|| PyRuntimeError::new_err("No CQE after wait"))?;
-        Ok(self.cqe_to_event(&cqe))
+    /// `push_entries`, even while shutting down (for follow-ups of live ops).
+    fn push_entries_unchecked(&mut self, entries: &[squeue::Entry]) -> PyResult<()> {
+        // SAFETY: we trust that the caller has set up the entries correctly and
+        // that any buffers referenced are pinned in `pinned`.
+        let mut pushed = unsafe { self.uring_mut()?.push_multiple(entries) };
+        if pushed.is_err() && self.auto_submit {
+            Python::attach(|py| self.submit(py))?;
+            // SAFETY: as above.
+            pushed = unsafe { self.uring_mut()?.push_multiple(entries) };
+        }
+        pushed.map_err(|_| SubmissionQueueFull::new_err("Submission queue is full"))?;
+        self.in_flight += entries.len() as u32;
+        if !self.deferred_releases.is_empty() {
+            // A reused user_data's new pins must survive the old op's release.
+            self.deferred_releases
+                .retain(|user_data| entries.iter().all(|e| e.get_user_data() != *user_data));
+        }
+        Ok(())
     }
 
-    /// Submit a no-op.
-    fn prep_nop(&mut self, user_data: u64) -> PyResult<()> {
-        let entry = opcode::Nop::new().build().user_data(user_data);
-        self.push_entry(entry)
+    /// `push_entries` for a 128-byte SQE, on an `sqe128=True` ring. It can't
+    /// be part of a `chain()`.
+    fn push_wide_entry(&mut self, entry: squeue::Entry128) -> PyResult<()> {
+        if self.shutting_down {
+            return Err(RingError::new_err("Ring is shutting down"));
+        }
+        if self.chain.is_some() {
+            return Err(RingError::new_err(
+                "128-byte SQEs can't be pushed inside a chain",
+            ));
+        }
+        if !self.sqe128 {
+            return Err(PyValueError::new_err(
+                "128-byte SQEs need a Ring(sqe128=True)",
+            ));
+        }
+        let wide = std::slice::from_ref(&entry);
+        // SAFETY: as in `push_entries_unchecked`.
+        let mut pushed = unsafe { self.uring_mut()?.push_wide(wide) };
+        if pushed.is_err() && self.auto_submit {
+            Python::attach(|py| self.submit(py))?;
+            // SAFETY: as above.
+            pushed = unsafe { self.uring_mut()?.push_wide(wide) };
+        }
+        pushed.map_err(|_| SubmissionQueueFull::new_err("Submission queue is full"))?;
+        self.in_flight += 1;
+        let user_data = wide_user_data(&entry);
+        self.deferred_releases
+            .retain(|pending| *pending != user_data);
+        Ok(())
     }
 
-    /// Submit a timeout (sleep).
-    fn prep_timeout(&mut self, user_data: u64, sec: u64, nsec: u32) -> PyResult<()> {
-        let timespec = types::Timespec::new().sec(sec).nsec(nsec);
-        self.pinned_timespecs.insert(user_data, timespec);
-        let ts = self.pinned_timespecs.get(&user_data).unwrap();
+    /// Push `entry` with `IO_LINK`, followed by a `LINK_TIMEOUT` that cancels it
+    /// after `sec`/`nsec`. Both are pushed, or neither.
+    ///
+    /// Returns the `(op, timeout)` user_data pair. The timeout's is the op's
+    /// with `LINK_TIMEOUT_TAG` (bit 63) set.
+    fn push_with_link_timeout(
+        &mut self,
+        entry: squeue::Entry,
+        sec: u64,
+        nsec: u32,
+    ) -> PyResult<(u64, u64)> {
+        let user_data = entry.get_user_data();
+        check_user_data(user_data)?;
+        let timeout_user_data = user_data | LINK_TIMEOUT_TAG;
 
-        let entry = opcode::Timeout::new(ts).build().user_data(user_data);
+        let ts = Box::new(types::Timespec::new().sec(sec).nsec(nsec));
+        let timeout = opcode::LinkTimeout::new(&*ts)
+            .build()
+            .user_data(timeout_user_data);
+        self.pin(timeout_user_data, Pinned::Timespec(ts));
 
-        self.push_entry(entry)
+        self.push_own_entries(&[entry.flags(squeue::Flags::IO_LINK), timeout])?;
+        Ok((user_data, timeout_user_data))
     }
 
-    /// Prep a read into `buf`.
-    /// The `buf` (a Python `bytearray`) is pinned until the CQE is consumed.
-    /// **Do not resize `buf` between prep and consuming the CQE.**
-    #[pyo3(signature = (user_data, fd, buf, nbytes, offset))]
-    fn prep_read(
+    /// The registered buffer ring of group `group_id`.
+    fn buf_ring(&self, group_id: u16) -> PyResult<&BufRing> {
+        self.buf_rings.get(&group_id).ok_or_else(|| {
+            PyValueError::new_err(format!("Buffer group {group_id} is not registered"))
+        })
+    }
+
+    /// Build a recv into `buf`, pinning it under `user_data`.
+    fn recv_entry(
         &mut self,
-        _py: Python<'_>,
         user_data: u64,
         fd: RawFd,
         buf: Bound<'_, PyByteArray>,
-        nbytes: u32,
-        offset: u64,
-    ) -> PyResult<()> {
+        flags: u32,
+        poll_first: bool,
+    ) -> PyResult<squeue::Entry> {
+        let flags = check_msg_flags(flags)?;
         let ptr = buf.data();
-        let len = nbytes.min(buf.len() as u32);
+        let len = buf.len() as u32;
 
-        let entry = opcode::Read::new(types::Fd(fd), ptr.cast(), len)
-            .offset(offset)
+        let mut entry = opcode::Recv::new(types::Fd(fd), ptr.cast(), len)
+            .flags(flags)
             .build()
             .user_data(user_data);
+        if poll_first {
+            entry = patch_sqe(entry, |sqe| sqe.ioprio |= IORING_RECVSEND_POLL_FIRST);
+        }
 
-        self.pinned_mutable_buffers.insert(user_data, buf.unbind());
-        self.push_entry(entry)
+        self.pin_mutable(user_data, buf);
+        Ok(entry)
     }
 
-    // Prepares statx for metadata extraction.
-    fn prep_statx(
+    /// Build a recv into a buffer picked from provided-buffer group `group_id`.
+    fn recv_select_entry(
         &mut self,
         user_data: u64,
-        path: &str,
-        buf: Bound<'_, StatxBuffer>,
-        flags: i32,
-        mask: u32,
-        dir_fd: RawFd,
-    ) -> PyResult<()> {
-        let c_path =
-            CString::new(path).map_err(|_| PyRuntimeError::new_err("Path contains null byte"))?;
-        let path_ptr = c_path.as_ptr();
-
-        let mut guard = buf.borrow_mut();
-        let statxbuf_ptr = &mut *guard.inner as *mut libc::statx as *mut io_uring::types::statx;
+        fd: RawFd,
+        group_id: u16,
+        flags: u32,
+        poll_first: bool,
+    ) -> PyResult<squeue::Entry> {
+        let flags = check_msg_flags(flags)?;
+        let len = self.buf_ring(group_id)?.buf_size;
 
-        let entry = opcode::Statx::new(types::Fd(dir_fd), path_ptr, statxbuf_ptr)
+        let mut entry = opcode::Recv::new(types::Fd(fd), ptr::null_mut(), len)
             .flags(flags)
-            .mask(mask)
+            .buf_group(group_id)
             .build()
+            .flags(squeue::Flags::BUFFER_SELECT)
             .user_data(user_data);
+        if poll_first {
+            entry = patch_sqe(entry, |sqe| sqe.ioprio |= IORING_RECVSEND_POLL_FIRST);
+        }
+
+        self.buffer_groups.insert(user_data, (group_id, false));
+        Ok(entry)
+    }
+
+    /// Build a send of the bytes left in a `prep_socket_send_all`.
+    fn send_all_entry(user_data: u64, state: &SendAllState) -> squeue::Entry {
+        let ptr = (state.addr + state.sent) as *const u8;
+        let len = (state.len - state.sent) as u32;
+        opcode::Send::new(types::Fd(state.fd), ptr, len)
+            .flags(state.flags)
+            .build()
+            .user_data(user_data)
+    }
+
+    /// Build a connect to `sock_addr`, pinning the address under `user_data`.
+    fn connect_entry(&mut self, user_data: u64, fd: RawFd, sock_addr: SockAddr) -> squeue::Entry {
+        let (ptr, len) = sock_addr.inner.as_ptr_and_len();
+        self.pin(user_data, Pinned::SockAddr(sock_addr.inner));
+        opcode::Connect::new(types::Fd(fd), ptr, len)
+            .build()
+            .user_data(user_data)
+    }
+
+    /// Pin `buf` for the op with `user_data`, sharing the pin with any other
+    /// in-flight op on the same bytearray.
+    fn pin_mutable(&mut self, user_data: u64, buf: Bound<'_, PyByteArray>) {
+        let key = buf.as_ptr() as usize;
+        self.shared_mutable_buffers
+            .entry(key)
+            .or_insert_with(|| SharedBuffer {
+                buf: buf.unbind(),
+                ops: 0,
+            })
+            .ops += 1;
+        self.pin(user_data, Pinned::MutableBuffer(key));
+    }
+
+    /// Hand `cookie` back on the completions of the op with `user_data`,
+    /// replacing any left over from an earlier op with the same one.
+    ///
+    /// Call it only once the op is pushed, so a prep that raises leaves the
+    /// cookie of a live op with the same `user_data` alone.
+    fn set_cookie(&mut self, user_data: u64, cookie: Option<Py<PyAny>>) {
+        match cookie {
+            Some(cookie) => {
+                self.cookies.insert(user_data, cookie);
+            }
+            None if !self.cookies.is_empty() => {
+                self.cookies.remove(&user_data);
+            }
+            None => {}
+        }
+    }
+
+    /// Pin `resource` until the final CQE of the op with `user_data`.
+    fn pin(&mut self, user_data: u64, resource: Pinned) {
+        if let Some(Pinned::MutableBuffer(key)) = self.pinned.insert(user_data, resource) {
+            self.unpin_mutable(key);
+        }
+    }
+
+    /// Drop one op's hold on the buffer at `key`, unpinning it after the last.
+    fn unpin_mutable(&mut self, key: usize) {
+        if let Some(shared) = self.shared_mutable_buffers.get_mut(&key) {
+            shared.ops -= 1;
+            if shared.ops == 0 {
+                self.shared_mutable_buffers.remove(&key);
+            }
+        }
+    }
+
+    /// Release any pinned resources associated with a completed user_data.
+    fn release_pinned(&mut self, user_data: u64) {
+        self.release_pinned_batch(&[user_data]);
+    }
+
+    /// `release_pinned` for several completed ops, one map at a time and
+    /// skipping maps with nothing pinned.
+    fn release_pinned_batch(&mut self, user_datas: &[u64]) {
+        fn remove_all<V>(map: &mut HashMap<u64, V>, keys: &[u64]) {
+            if !map.is_empty() {
+                for key in keys {
+                    map.remove(key);
+                }
+            }
+        }
+
+        if !self.pinned.is_empty() {
+            for user_data in user_datas {
+                if let Some(Pinned::MutableBuffer(key)) = self.pinned.remove(user_data) {
+                    self.unpin_mutable(key);
+                }
+            }
+        }
+        remove_all(&mut self.buffer_groups, user_datas);
+        remove_all(&mut self.cookies, user_datas);
+        remove_all(&mut self.send_alls, user_datas);
+        if !self.send_bundles.is_empty() {
+            for user_data in user_datas {
+                if let Some(bundle) = self.send_bundles.remove(user_data)
+                    && let Some(ring) = &self.ring
+                {
+                    // Nothing to do on failure: the ring memory has to go either way.
+                    let _ = ring.submitter().unregister_buf_ring(bundle.group_id);
+                }
+            }
+        }
+        if !self.listener_slots.is_empty() {
+            for user_data in user_datas {
+                if let Some(slot) = self.listener_slots.remove(user_data) {
+                    self.free_listener_slot(slot);
+                }
+            }
+        }
+        if let Some(pool) = &self.buffer_pool {
+            let pool = pool.get();
+            for user_data in user_datas {
+                pool.free(*user_data);
+            }
+        }
+    }
+
+    /// Take a free direct descriptor slot for `prep_listener`, registering
+    /// the (sparse) table on first use.
+    fn take_listener_slot(&mut self) -> PyResult<u32> {
+        if self.file_table_len.is_some() {
+            return Err(PyValueError::new_err(
+                "prep_listener needs its own file table, but one was registered \
+                 with register_files_sparse",
+            ));
+        }
+        if self.listener_slots_free.is_none() {
+            self.uring()?
+                .submitter()
+                .register_files_sparse(LISTENER_FILE_SLOTS)
+                .map_err(|e| RingError::new_err(format!("io_uring_register_files failed: {e}")))?;
+            self.listener_slots_free = Some((0..LISTENER_FILE_SLOTS).rev().collect());
+        }
+        self.listener_slots_free
+            .as_mut()
+            .and_then(Vec::pop)
+            .ok_or_else(|| {
+                RingError::new_err(format!(
+                    "All {LISTENER_FILE_SLOTS} listener slots are in use; reap pending prep_listener ops"
+                ))
+            })
+    }
+
+    /// Empty a `prep_listener` slot (the socket lives on as a regular fd, if
+    /// installed) and make it available again.
+    fn free_listener_slot(&mut self, slot: u32) {
+        if let Some(ring) = &self.ring {
+            // Nothing to do on failure: at worst the slot keeps a socket open.
+            let _ = ring.submitter().register_files_update(slot, &[-1]);
+        }
+        if let Some(free) = self.listener_slots_free.as_mut() {
+            free.push(slot);
+        }
+    }
+
+    /// Whether the kernel still holds back CQEs (`IORING_FEAT_NODROP`) after
+    /// moving as many as fit into the CQ.
+    fn cq_backlogged(&mut self) -> PyResult<bool> {
+        let ring = self.uring_mut()?;
+        if !ring.params().is_feature_nodrop() || !ring.cq_overflow().1 {
+            return Ok(false);
+        }
+        // SAFETY: no SQEs are submitted and no argument is passed.
+        unsafe {
+            ring.submitter()
+                .enter::<libc::sigset_t>(0, 0, IORING_ENTER_GETEVENTS, None)
+        }
+        .map_err(|e| RingCompletionError::new_err(format!("io_uring_enter failed: {e}")))?;
+        Ok(ring.cq_overflow().1)
+    }
+
+    /// Submit until the SQ is empty, so no prepped SQE is left behind when
+    /// blocking. Without `IORING_SETUP_SUBMIT_ALL`, the kernel stops a
+    /// submit at the first SQE that fails to prep and leaves the rest queued.
+    fn flush_sq(&mut self, py: Python<'_>) -> PyResult<()> {
+        if self.sqpoll_idle_ms.is_some() {
+            // The poller thread consumes the SQ by itself.
+            return Ok(());
+        }
+        let ring = self.uring_mut()?;
+        while ring.sq_pending().0 > 0 {
+            match py.detach(|| ring.submit()) {
+                Ok(n) if n > 0 => {}
+                // No progress (e.g. `EBUSY` on a full CQ): the wait reports it.
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Submit, then block until `want` CQEs are ready, with the GIL released.
+    ///
+    /// A signal interrupting the wait runs the Python signal handlers, as for
+    /// any blocking call (PEP 475): if one raises, so does this; otherwise
+    /// the wait resumes.
+    fn submit_and_wait(&mut self, py: Python<'_>, want: usize) -> PyResult<()> {
+        self.flush_sq(py)?;
+        loop {
+            let ring = self.uring_mut()?;
+            match py.detach(|| ring.submit_and_wait(want)) {
+                Ok(_) => return Ok(()),
+                Err(e) if e.raw_os_error() == Some(libc::EINTR) => py.check_signals()?,
+                Err(e) => {
+                    return Err(RingCompletionError::new_err(format!(
+                        "io_uring_wait failed: {e}"
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Submit, then block until a CQE is ready, `deadline` passes, or a
+    /// signal interrupts the wait; callers re-check what they wait for.
+    ///
+    /// Returns false without waiting once `deadline` has passed.
+    fn wait_cqe_until(&mut self, py: Python<'_>, deadline: Option<Instant>) -> PyResult<bool> {
+        let remaining = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(remaining) if !remaining.is_zero() => Some(remaining),
+                _ => return Ok(false),
+            },
+            None => None,
+        };
+        let ring = self.uring_mut()?;
+        let waited = match remaining {
+            Some(remaining) => {
+                let ts = types::Timespec::from(remaining);
+                let args = types::SubmitArgs::new().timespec(&ts);
+                py.detach(|| ring.submitter().submit_with_args(1, &args))
+            }
+            None => py.detach(|| ring.submit_and_wait(1)),
+        };
+        match waited {
+            Ok(_) => Ok(true),
+            Err(e) if e.raw_os_error() == Some(libc::ETIME) => Ok(true),
+            Err(e) if e.raw_os_error() == Some(libc::EINTR) => {
+                py.check_signals()?;
+                Ok(true)
+            }
+            Err(e) => Err(RingCompletionError::new_err(format!(
+                "io_uring_wait failed: {e}"
+            ))),
+        }
+    }
+
+    /// Ops whose final completion is still in the kernel or buffered.
+    fn pending_ops(&self) -> u32 {
+        let buffered = self
+            .completions
+            .iter()
+            .filter(|event| !cqueue::more(event.flags) && event.user_data != WAKE_USER_DATA)
+            .count();
+        self.in_flight + buffered as u32
+    }
+
+    /// Release every pin and drop the io_uring instance.
+    fn close(&mut self) {
+        self.pinned.clear();
+        self.shared_mutable_buffers.clear();
+        self.buffer_groups.clear();
+        self.cookies.clear();
+        self.send_alls.clear();
+        self.deferred_releases.clear();
+        self.listener_slots.clear();
+        self.listener_slots_free = None;
+        self.file_table_len = None;
+        self.completions.clear();
+        self.ring = None; // Drop triggers internal io_uring cleanup
+        // Buffer rings and the buffer pool must outlive the io_uring instance
+        // they're registered with.
+        self.buf_rings.clear();
+        self.send_bundles.clear();
+        self.buffer_pool = None;
+        self.eventfd = None;
+        self.in_flight = 0;
+        self.cq_dropped = 0;
+        self.chain = None;
+    }
+
+    /// Set up an io_uring instance as configured, with `S`-sized SQEs and
+    /// `C`-sized CQEs.
+    fn build_uring<S: squeue::EntryMarker, C: cqueue::EntryMarker>(
+        &self,
+    ) -> std::io::Result<IoUring<S, C>> {
+        let mut builder = IoUring::<S, C>::builder();
+        if self.r_disabled {
+            builder.setup_r_disabled();
+        }
+        if let Some(idle_ms) = self.sqpoll_idle_ms {
+            builder.setup_sqpoll(idle_ms);
+        }
+        if self.clamp {
+            builder.setup_clamp();
+        }
+        if self.defer_taskrun {
+            builder.setup_single_issuer().setup_defer_taskrun();
+        }
+        if self.coop_taskrun {
+            builder.setup_coop_taskrun().setup_taskrun_flag();
+        }
+        builder.build(self.depth)
+    }
+
+    /// Reap every ready CQE into `completions`.
+    ///
+    /// Warns (`RuntimeWarning`) if the kernel dropped CQEs since the last reap.
+    fn reap(&mut self) -> PyResult<()> {
+        let (dropped, _) = self.uring_mut()?.cq_overflow();
+        if dropped != self.cq_dropped {
+            let lost = dropped.wrapping_sub(self.cq_dropped);
+            self.cq_dropped = dropped;
+            let message = CString::new(format!(
+                "{lost} completion(s) dropped on a full completion queue; reap more often"
+            ))
+            .expect("message has no null byte");
+            Python::attach(|py| PyErr::warn(py, &py.get_type::<PyRuntimeWarning>(), &message, 1))?;
+        }
+        let cqes = self.uring_mut()?.completions(usize::MAX, true);
+        for cqe in &cqes {
+            if let Some(event) = self.cqe_to_event(cqe) {
+                self.completions.push_back(event);
+            }
+        }
+        Ok(())
+    }
+
+    /// Resubmit the rest of a `prep_socket_send_all` after a short send.
+    ///
+    /// Returns `None` if the op goes on, else the `res` to report: the total
+    /// sent, or the error. Other ops' `res` passes through.
+    fn continue_send_all(&mut self, user_data: u64, res: i32) -> Option<i32> {
+        let Some(state) = self.send_alls.get_mut(&user_data) else {
+            return Some(res);
+        };
+        if res > 0 && state.sent + (res as usize) < state.len {
+            state.sent += res as usize;
+            let entry = Self::send_all_entry(user_data, state);
+            if self.push_entries_unchecked(&[entry]).is_ok() {
+                self.in_flight -= 1;
+                return None;
+            }
+            // No room to continue: complete early with the short count.
+            return Some(self.send_alls[&user_data].sent as i32);
+        }
+        Some(if res >= 0 {
+            res + state.sent as i32
+        } else {
+            res
+        })
+    }
+
+    /// `completions_into` for an `out` of element type `T`, with `convert`
+    /// mapping each (sign-extended) value to it.
+    fn fill_completions<T: Element + Copy>(
+        &mut self,
+        py: Python<'_>,
+        out: &PyBuffer<T>,
+        convert: impl Fn(u64) -> T,
+    ) -> PyResult<usize> {
+        let cells = out
+            .as_mut_slice(py)
+            .ok_or_else(|| PyValueError::new_err("out must be writable and C-contiguous"))?;
+        if cells.len() % 3 != 0 {
+            return Err(PyValueError::new_err(format!(
+                "out holds {} values, not a whole number of (user_data, res, flags) rows",
+                cells.len()
+            )));
+        }
+        let mut written = 0;
+        for row in cells.chunks_exact(3) {
+            let event = loop {
+                if let Some(event) = self.completions.pop_front() {
+                    break Some(event);
+                }
+                let Some(cqe) = self.uring_mut()?.completions(1, true).pop() else {
+                    break None;
+                };
+                if let Some(event) = self.cqe_to_event(&cqe) {
+                    break Some(event);
+                }
+            };
+            let Some(event) = event else { break };
+            row[0].set(convert(event.user_data));
+            row[1].set(convert(event.res as i64 as u64));
+            row[2].set(convert(event.flags as u64));
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Turn a CQE into the event handed to Python, releasing what the op
+    /// pinned. Returns `None` for CQEs that are only internal bookkeeping.
+    fn cqe_to_event(&mut self, (cqe, big_cqe): &Cqe) -> Option<CompletionEvent> {
+        let user_data = cqe.user_data();
+        let flags = cqe.flags();
+        let res = self.continue_send_all(user_data, cqe.result())?;
+        let buffer_ids = cqueue::buffer_select(flags).and_then(|bid| {
+            let &(group, bundle) = self.buffer_groups.get(&user_data)?;
+            Some(
+                self.buf_rings
+                    .get_mut(&group)?
+                    .consumed(bid, cqe.result(), bundle),
+            )
+        });
+        if cqe.result() < 0
+            && user_data & SO_ERROR_TAG == 0
+            && let Some(Pinned::SocketError(error)) = self.pinned.get(&(user_data | SO_ERROR_TAG))
+        {
+            // A failed connect consumes the socket error, so SO_ERROR alone reads 0.
+            error
+                .get()
+                .connect_errno
+                .store(-cqe.result(), Ordering::Release);
+        }
+        let cookie = if self.cookies.is_empty() {
+            None
+        } else if cqueue::more(flags) {
+            let cookie = self.cookies.get(&user_data);
+            Python::attach(|py| cookie.map(|cookie| cookie.clone_ref(py)))
+        } else {
+            self.cookies.remove(&user_data)
+        };
+        // Multishot ops stay live (and keep their pins) while F_MORE is set.
+        if !cqueue::more(flags) && user_data != WAKE_USER_DATA {
+            self.release_pinned(user_data);
+            self.in_flight = self.in_flight.saturating_sub(1);
+        }
+        if user_data == SHUTDOWN_CANCEL_USER_DATA || user_data == WAIT_UNTIL_USER_DATA {
+            return None;
+        }
+        Some(CompletionEvent {
+            user_data,
+            res,
+            flags,
+            completed_at_ns: self.timestamps.then(monotonic_ns),
+            buffer_ids,
+            big_cqe: big_cqe.map(|[a, b]| (a, b)),
+            cookie,
+        })
+    }
+}
+
+/// Deadline `timeout` seconds from now, if any.
+fn deadline_after(timeout: Option<f64>) -> Option<Instant> {
+    timeout.map(|sec| Instant::now() + Duration::from_secs_f64(sec))
+}
+
+/// Timeout flags `prep_timeouts` takes.
+const TIMEOUTS_FLAGS: types::TimeoutFlags = types::TimeoutFlags::ABS
+    .union(types::TimeoutFlags::BOOTTIME)
+    .union(types::TimeoutFlags::REALTIME)
+    .union(types::TimeoutFlags::ETIME_SUCCESS);
+
+/// Timeout flags selecting `clock`, one of the clocks a timeout op can use.
+fn timeout_clock_flags(clock: libc::clockid_t) -> PyResult<types::TimeoutFlags> {
+    match clock {
+        libc::CLOCK_MONOTONIC => Ok(types::TimeoutFlags::empty()),
+        libc::CLOCK_BOOTTIME => Ok(types::TimeoutFlags::BOOTTIME),
+        libc::CLOCK_REALTIME => Ok(types::TimeoutFlags::REALTIME),
+        _ => Err(PyValueError::new_err(
+            "Timeout clock must be CLOCK_MONOTONIC, CLOCK_BOOTTIME or CLOCK_REALTIME",
+        )),
+    }
+}
+
+/// Reject a negative fd, most often one that was already closed, before it
+/// reaches the kernel as a confusing `-EBADF`.
+fn check_fd(method: &str, fd: RawFd) -> PyResult<()> {
+    if fd < 0 {
+        return Err(PyValueError::new_err(format!(
+            "{method}: fd {fd} is negative (was it already closed?)"
+        )));
+    }
+    Ok(())
+}
+
+/// Reject a user_data with either of the ring's tag bits set, which would be
+/// mixed up with its own ops.
+fn check_user_data(user_data: u64) -> PyResult<()> {
+    if user_data > MAX_USER_DATA {
+        return Err(PyValueError::new_err(format!(
+            "user_data {user_data} is reserved (the maximum is MAX_USER_DATA = {MAX_USER_DATA})"
+        )));
+    }
+    Ok(())
+}
+
+/// Every `MSG_*` flag Linux knows for send/recv.
+const KNOWN_MSG_FLAGS: i32 = libc::MSG_OOB
+    | libc::MSG_PEEK
+    | libc::MSG_DONTROUTE
+    | libc::MSG_CTRUNC
+    | libc::MSG_TRUNC
+    | libc::MSG_DONTWAIT
+    | libc::MSG_EOR
+    | libc::MSG_WAITALL
+    | libc::MSG_CONFIRM
+    | libc::MSG_ERRQUEUE
+    | libc::MSG_NOSIGNAL
+    | libc::MSG_MORE
+    | libc::MSG_WAITFORONE
+    | libc::MSG_FASTOPEN
+    | libc::MSG_CMSG_CLOEXEC
+    | libc::MSG_ZEROCOPY;
+
+/// Validate send/recv flags, rejecting bits that aren't a known `MSG_*` flag.
+fn check_msg_flags(flags: u32) -> PyResult<i32> {
+    let flags = flags as i32;
+    let unknown = flags & !KNOWN_MSG_FLAGS;
+    if unknown != 0 {
+        return Err(PyValueError::new_err(format!(
+            "Unknown MSG_* flag bits {unknown:#x}"
+        )));
+    }
+    Ok(flags)
+}
+
+/// Map a Python file offset to the SQE's, where -1 (`u64::MAX`) means
+/// "use the current file position".
+fn file_offset(offset: i64) -> PyResult<u64> {
+    match offset {
+        -1 => Ok(u64::MAX),
+        0.. => Ok(offset as u64),
+        _ => Err(PyValueError::new_err(format!(
+            "Invalid offset {offset} (must be >= 0, or -1 for the file position)"
+        ))),
+    }
+}
+
+/// `name` of an xattr op as a C string.
+fn xattr_name(name: &str) -> PyResult<CString> {
+    CString::new(name).map_err(|_| PyValueError::new_err("Attribute name contains null byte"))
+}
+
+/// Length of an xattr value buffer as the kernel takes it.
+fn xattr_len(len: usize) -> PyResult<u32> {
+    u32::try_from(len).map_err(|_| PyValueError::new_err("Xattr value is too large"))
+}
+
+/// Validate socket creation flags, which may only be `SOCK_NONBLOCK`/`SOCK_CLOEXEC`.
+fn check_socket_flags(flags: u32) -> PyResult<i32> {
+    let flags = flags as i32;
+    if flags & !(libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC) != 0 {
+        return Err(PyValueError::new_err(
+            "Socket flags may only contain SOCK_NONBLOCK and SOCK_CLOEXEC",
+        ));
+    }
+    Ok(flags)
+}
+
+/// Set the write lifetime hint of the file behind `fd`.
+fn set_write_hint(fd: RawFd, hint: u64) -> PyResult<()> {
+    if hint > RWH_WRITE_LIFE_EXTREME {
+        return Err(PyValueError::new_err(format!(
+            "Invalid write hint {hint}, expected a RWH_WRITE_LIFE_* value"
+        )));
+    }
+    // SAFETY: F_SET_RW_HINT reads a u64 through the pointer.
+    if unsafe { libc::fcntl(fd, F_SET_RW_HINT, &hint as *const u64) } < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Current `CLOCK_MONOTONIC` time in nanoseconds, same clock as `time.monotonic_ns()`.
+fn monotonic_ns() -> u64 {
+    let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+    // SAFETY: `ts` is a valid, writable timespec.
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+#[pymethods]
+impl Ring {
+    #[new]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        depth = 32,
+        *,
+        r_disabled = false,
+        timestamps = false,
+        sqpoll_idle_ms = None,
+        defer_taskrun = false,
+        coop_taskrun = false,
+        auto_submit = false,
+        clamp = false,
+        cqe32 = false,
+        sqe128 = false,
+    ))]
+    fn new(
+        depth: u32,
+        r_disabled: bool,
+        timestamps: bool,
+        sqpoll_idle_ms: Option<u32>,
+        defer_taskrun: bool,
+        coop_taskrun: bool,
+        auto_submit: bool,
+        clamp: bool,
+        cqe32: bool,
+        sqe128: bool,
+    ) -> PyResult<Self> {
+        if depth == 0 {
+            return Err(PyValueError::new_err(
+                "Ring depth must be at least 1 (the kernel rounds it up to a power of \
+                 two; see sq_entries for the allocated size)",
+            ));
+        }
+        Ok(Ring {
+            ring: None,
+            depth,
+            r_disabled,
+            sqpoll_idle_ms,
+            timestamps,
+            defer_taskrun,
+            coop_taskrun,
+            auto_submit,
+            clamp,
+            cqe32,
+            sqe128,
+            pinned: HashMap::new(),
+            shared_mutable_buffers: HashMap::new(),
+            buffer_groups: HashMap::new(),
+            cookies: HashMap::new(),
+            buf_rings: HashMap::new(),
+            send_bundles: HashMap::new(),
+            send_alls: HashMap::new(),
+            deferred_releases: Vec::new(),
+            listener_slots_free: None,
+            listener_slots: HashMap::new(),
+            file_table_len: None,
+            completions: VecDeque::new(),
+            eventfd: None,
+            buffer_pool: None,
+            in_flight: 0,
+            cq_dropped: 0,
+            wait_timeout: None,
+            shutting_down: false,
+            chain: None,
+        })
+    }
+
+    /// Python CM protocol.
+    fn __enter__(mut slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        let ring = match (slf.sqe128, slf.cqe32) {
+            (false, false) => slf.build_uring().map(Uring::Sqe64Cqe16),
+            (false, true) => slf.build_uring().map(Uring::Sqe64Cqe32),
+            (true, false) => slf.build_uring().map(Uring::Sqe128Cqe16),
+            (true, true) => slf.build_uring().map(Uring::Sqe128Cqe32),
+        };
+        let ring = ring.map_err(ring_setup_error)?;
+        // Size the pin map for a full SQ up front, so it doesn't rehash
+        // under load.
+        let depth = ring.params().sq_entries() as usize;
+        slf.pinned.reserve(depth);
+        slf.ring = Some(ring);
+        slf.shutting_down = false;
+        Ok(slf)
+    }
+
+    #[pyo3(signature = (_exc_type=None, _exc_val=None, _exc_tb=None))]
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<&Bound<'_, PyAny>>,
+        _exc_val: Option<&Bound<'_, PyAny>>,
+        _exc_tb: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<bool> {
+        self.close();
+        Ok(false)
+    }
+
+    /// Async CM protocol: `async with Ring() as ring:` sets up and tears down
+    /// like the sync one, without blocking. Waiting for completions stays
+    /// explicit. See `rusty_ring._asyncio`.
+    fn __aenter__<'py>(slf: Bound<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let aenter = slf.py().import("rusty_ring._asyncio")?.getattr("aenter")?;
+        aenter.call1((slf,))
+    }
+
+    #[pyo3(signature = (exc_type=None, exc_val=None, exc_tb=None))]
+    fn __aexit__<'py>(
+        slf: Bound<'py, Self>,
+        exc_type: Option<Bound<'py, PyAny>>,
+        exc_val: Option<Bound<'py, PyAny>>,
+        exc_tb: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let aexit = slf.py().import("rusty_ring._asyncio")?.getattr("aexit")?;
+        aexit.call1((slf, exc_type, exc_val, exc_tb))
+    }
+
+    fn __repr__(&self) -> String {
+        match &self.ring {
+            Some(ring) => format!(
+                "Ring(depth={}, open=True, in_flight={}, sq_space={})",
+                self.depth,
+                self.pending_ops(),
+                ring.sq_space()
+            ),
+            None => format!("Ring(depth={}, open=False)", self.depth),
+        }
+    }
+
+    /// Number of in-flight ops, same as `in_flight`.
+    fn __len__(&self) -> usize {
+        self.pending_ops() as usize
+    }
+
+    /// A ring is truthy even with nothing in flight.
+    fn __bool__(&self) -> bool {
+        true
+    }
+
+    /// Requested SQ depth (the kernel rounds it up to a power of two, see
+    /// `sq_entries`).
+    #[getter]
+    fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Whether the io_uring instance is set up (inside the context manager).
+    #[getter]
+    fn is_open(&self) -> bool {
+        self.ring.is_some()
+    }
+
+    /// Whether the ring fd is close-on-exec, which the kernel always sets.
+    ///
+    /// That covers `exec`, not `fork`: a forked child still shares this
+    /// ring with its parent. Don't use it (or close it) in the child; open a
+    /// new `Ring` there instead.
+    #[getter]
+    fn close_on_exec(&self) -> PyResult<bool> {
+        let fd = self.uring()?.as_raw_fd();
+        // SAFETY: F_GETFD takes no argument.
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+        if flags < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(flags & libc::FD_CLOEXEC != 0)
+    }
+
+    /// The ring's own fd. It polls readable (`POLLIN`) while CQEs are ready
+    /// to reap, so another ring can `prep_poll_add` it, as can `select` or
+    /// an event loop.
+    fn fileno(&self) -> PyResult<RawFd> {
+        Ok(self.uring()?.as_raw_fd())
+    }
+
+    /// Number of pushed ops whose final completion hasn't been returned yet,
+    /// including completions buffered by `wait_for`.
+    #[getter]
+    fn in_flight(&self) -> u32 {
+        self.pending_ops()
+    }
+
+    /// Make room for `additional` more in-flight ops in the map that pins
+    /// their buffers and arguments, so it doesn't grow (and rehash) while
+    /// ops are being prepped.
+    ///
+    /// Entering the ring already makes room for `sq_entries` ops; reserve
+    /// beyond that when more ops stay in flight at once.
+    fn reserve(&mut self, additional: usize) {
+        self.pinned.reserve(additional);
+    }
+
+    /// SQ size the kernel actually allocated: `depth` rounded up to a power
+    /// of two, or the kernel's maximum with `clamp=True`. Budget SQ slots
+    /// against this rather than `depth`.
+    #[getter]
+    fn sq_entries(&self) -> PyResult<u32> {
+        Ok(self.uring()?.params().sq_entries())
+    }
+
+    /// CQ size the kernel actually allocated, twice `sq_entries`.
+    #[getter]
+    fn cq_entries(&self) -> PyResult<u32> {
+        Ok(self.uring()?.params().cq_entries())
+    }
+
+    /// The `io_uring_params` the kernel filled in at setup, for diagnostics.
+    fn params(&self) -> PyResult<RingParams> {
+        // SAFETY: `Parameters` is a transparent wrapper over
+        // `io_uring_params`, which has the same layout as `RawParams`.
+        let raw: &RawParams = unsafe { &*(self.uring()?.params() as *const Parameters).cast() };
+        Ok(RingParams {
+            sq_entries: raw.sq_entries,
+            cq_entries: raw.cq_entries,
+            flags: raw.flags,
+            features: raw.features,
+            sq_thread_cpu: raw.sq_thread_cpu,
+            sq_thread_idle: raw.sq_thread_idle,
+            sq_off: named_offsets(&SQ_OFF_FIELDS, &raw.sq_off),
+            cq_off: named_offsets(&CQ_OFF_FIELDS, &raw.cq_off),
+        })
+    }
+
+    /// Restrict the ring to a whitelist of SQE opcodes.
+    ///
+    /// Only valid on a ring created with `r_disabled=True`, and only once.
+    /// Any opcode outside `allowed_opcodes`, or any SQE flag outside
+    /// `allowed_sqe_flags`, is rejected by the kernel with `-EACCES`.
+    #[pyo3(signature = (allowed_opcodes, allowed_sqe_flags = 0))]
+    fn register_restrictions(
+        &mut self,
+        allowed_opcodes: Vec<u8>,
+        allowed_sqe_flags: u8,
+    ) -> PyResult<()> {
+        let mut restrictions: Vec<Restriction> = allowed_opcodes
+            .into_iter()
+            .map(Restriction::sqe_op)
+            .collect();
+        restrictions.push(Restriction::sqe_flags_allowed(allowed_sqe_flags));
+
+        self.uring_mut()?
+            .submitter()
+            .register_restrictions(&mut restrictions)
+            .map_err(|e| RingError::new_err(format!("io_uring_register_restrictions failed: {e}")))
+    }
+
+    /// Enable a ring created with `r_disabled=True`.
+    ///
+    /// The ordering is: create disabled -> register restrictions (and any
+    /// file/buffer tables) -> `enable()` -> submit. Submissions before
+    /// `enable()` fail with `-EBADFD`.
+    fn enable(&mut self) -> PyResult<()> {
+        self.uring_mut()?
+            .submitter()
+            .register_enable_rings()
+            .map_err(|e| RingError::new_err(format!("io_uring_enable_rings failed: {e}")))
+    }
+
+    /// Register the calling thread's current credentials with the ring.
+    ///
+    /// Returns a personality id; ops prepped with `personality=<id>` run
+    /// under these credentials, even after the process has dropped them.
+    fn register_personality(&self) -> PyResult<u16> {
+        self.uring()?
+            .submitter()
+            .register_personality()
+            .map_err(|e| RingError::new_err(format!("io_uring_register_personality failed: {e}")))
+    }
+
+    /// Cap the ring's io-wq worker threads, per NUMA node.
+    ///
+    /// Bounded workers serve regular file/block I/O, unbounded ones ops that
+    /// may block indefinitely (e.g. sockets). 0 leaves a limit unchanged.
+    /// Returns the previous `(bounded, unbounded)` limits.
+    fn register_iowq_max_workers(&self, bounded: u32, unbounded: u32) -> PyResult<(u32, u32)> {
+        let mut workers = [bounded, unbounded];
+        self.uring()?
+            .submitter()
+            .register_iowq_max_workers(&mut workers)
+            .map_err(|e| {
+                RingError::new_err(format!("io_uring_register_iowq_max_workers failed: {e}"))
+            })?;
+        Ok((workers[0], workers[1]))
+    }
+
+    /// The current `(bounded, unbounded)` io-wq worker limits, per NUMA
+    /// node, as set by `register_iowq_max_workers` or the kernel defaults.
+    /// These are caps, not the number of workers running.
+    fn iowq_max_workers(&self) -> PyResult<(u32, u32)> {
+        self.register_iowq_max_workers(0, 0)
+    }
+
+    /// An eventfd the kernel signals whenever it posts a CQE to this ring,
+    /// for waking an event loop. It's non-blocking, and closed with the ring.
+    ///
+    /// Registered on the first call; later calls return the same fd.
+    fn register_eventfd(&mut self) -> PyResult<RawFd> {
+        if let Some(eventfd) = &self.eventfd {
+            return Ok(eventfd.as_raw_fd());
+        }
+        // SAFETY: plain syscall; the fd is owned below.
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            let e = std::io::Error::last_os_error();
+            return Err(RingError::new_err(format!("eventfd failed: {e}")));
+        }
+        // SAFETY: `fd` was just created and nothing else owns it.
+        let eventfd = unsafe { OwnedFd::from_raw_fd(fd) };
+        self.uring()?
+            .submitter()
+            .register_eventfd(fd)
+            .map_err(|e| RingError::new_err(format!("io_uring_register_eventfd failed: {e}")))?;
+        self.eventfd = Some(eventfd);
+        Ok(fd)
+    }
+
+    /// A `Waker` for ending a blocking `wait()` on this ring from another
+    /// thread, which can't call into the ring while it waits.
+    fn waker(&self) -> PyResult<Waker> {
+        // SAFETY: the ring fd is open while `self.uring()` is.
+        let target = unsafe { BorrowedFd::borrow_raw(self.uring()?.as_raw_fd()) }
+            .try_clone_to_owned()
+            .map_err(|e| RingError::new_err(format!("dup of the ring fd failed: {e}")))?;
+        let ring = IoUring::new(2).map_err(ring_setup_error)?;
+        Ok(Waker {
+            ring: Mutex::new(ring),
+            target,
+        })
+    }
+
+    /// Pin the ring's io-wq worker threads to `cpus`, a set of CPU numbers
+    /// as for `os.sched_setaffinity()`.
+    fn register_iowq_aff(&self, cpus: &Bound<'_, PyAny>) -> PyResult<()> {
+        // SAFETY: an all-zero cpu_set_t is the empty set.
+        let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        for cpu in cpus.try_iter()? {
+            let cpu: usize = cpu?.extract()?;
+            if cpu >= libc::CPU_SETSIZE as usize {
+                return Err(PyValueError::new_err(format!(
+                    "CPU {cpu} is out of range (CPU_SETSIZE is {})",
+                    libc::CPU_SETSIZE
+                )));
+            }
+            // SAFETY: `cpu` was checked to be within the set.
+            unsafe { libc::CPU_SET(cpu, &mut set) };
+        }
+        self.uring()?
+            .submitter()
+            .register_iowq_aff(&set)
+            .map_err(|e| RingError::new_err(format!("io_uring_register_iowq_aff failed: {e}")))
+    }
+
+    /// Let the ring's io-wq worker threads run on any CPU again.
+    fn unregister_iowq_aff(&self) -> PyResult<()> {
+        self.uring()?
+            .submitter()
+            .unregister_iowq_aff()
+            .map_err(|e| RingError::new_err(format!("io_uring_unregister_iowq_aff failed: {e}")))
+    }
+
+    /// Register a ring of `entries` provided buffers, `buf_size` bytes each,
+    /// as buffer group `group_id`.
+    ///
+    /// `entries` must be a power of two (at most 32768). All buffers start
+    /// out available to the kernel; once picked by an op, a buffer stays
+    /// consumed until it is handed back with `recycle_buffer`.
+    fn register_buf_ring(&mut self, group_id: u16, entries: u16, buf_size: u32) -> PyResult<()> {
+        if self.buf_rings.contains_key(&group_id) {
+            return Err(PyValueError::new_err(format!(
+                "Buffer group {group_id} is already registered"
+            )));
+        }
+        let buf_ring = BufRing::new(entries, buf_size)?;
+
+        // SAFETY: the ring memory is page aligned and lives in `self.buf_rings`
+        // until after the io_uring instance is dropped.
+        unsafe {
+            self.uring_mut()?
+                .submitter()
+                .register_buf_ring_with_flags(buf_ring.ring_addr(), entries, group_id, 0)
+                .map_err(|e| {
+                    RingError::new_err(format!("io_uring_register_buf_ring failed: {e}"))
+                })?;
+        }
+        self.buf_rings.insert(group_id, buf_ring);
+        Ok(())
+    }
+
+    /// Copy the first `nbytes` of provided buffer `buffer_id` in group `group_id`.
+    fn read_buffer<'py>(
+        &self,
+        py: Python<'py>,
+        group_id: u16,
+        buffer_id: u16,
+        nbytes: u32,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let buf_ring = self.buf_ring(group_id)?;
+        buf_ring.buffer(py, buffer_id, nbytes)
+    }
+
+    /// Hand provided buffer `buffer_id` of group `group_id` back to the
+    /// kernel once its data has been processed, e.g. after `read_buffer`.
+    ///
+    /// Each buffer a completion reports in `buffer_ids` must be recycled,
+    /// or the group runs dry and ops selecting from it fail with `-ENOBUFS`.
+    /// Recycling a buffer that isn't consumed raises `ValueError`.
+    fn recycle_buffer(&mut self, group_id: u16, buffer_id: u16) -> PyResult<()> {
+        self.buf_rings
+            .get_mut(&group_id)
+            .ok_or_else(|| {
+                PyValueError::new_err(format!("Buffer group {group_id} is not registered"))
+            })?
+            .recycle(buffer_id)
+    }
+
+    /// Register `count` fixed buffers of `buf_size` bytes each and return
+    /// the pool handing them out.
+    ///
+    /// A ring has at most one pool. Pair `pool.acquire(user_data)` with
+    /// `prep_read_fixed`; the buffer returns to the pool when the op's CQE
+    /// is reaped.
+    fn register_buffer_pool(
+        &mut self,
+        py: Python<'_>,
+        count: u16,
+        buf_size: u32,
+    ) -> PyResult<Py<BufferPool>> {
+        if self.buffer_pool.is_some() {
+            return Err(PyValueError::new_err("A buffer pool is already registered"));
+        }
+        let pool = BufferPool::new(count, buf_size)?;
+
+        // SAFETY: the buffers live in `self.buffer_pool` until after the
+        // io_uring instance is dropped.
+        unsafe {
+            self.uring()?
+                .submitter()
+                .register_buffers(&pool.iovecs())
+                .map_err(|e| {
+                    RingError::new_err(format!("io_uring_register_buffers failed: {e}"))
+                })?;
+        }
+        let pool = Py::new(py, pool)?;
+        self.buffer_pool = Some(pool.clone_ref(py));
+        Ok(pool)
+    }
+
+    /// Register a table of `count` fixed-file slots, all empty. Fill them
+    /// with `register_files_update`; `prep_close(file_index=...)` empties one.
+    ///
+    /// A ring has at most one file table, and that includes the one
+    /// `prep_listener` registers on first use.
+    fn register_files_sparse(&mut self, count: u32) -> PyResult<()> {
+        if self.file_table_len.is_some() || self.listener_slots_free.is_some() {
+            return Err(PyValueError::new_err("A file table is already registered"));
+        }
+        self.uring()?
+            .submitter()
+            .register_files_sparse(count)
+            .map_err(|e| RingError::new_err(format!("io_uring_register_files failed: {e}")))?;
+        self.file_table_len = Some(count);
+        Ok(())
+    }
+
+    /// Put `fds` in consecutive file table slots from `offset` on, -1
+    /// emptying a slot. Returns the number of slots updated.
+    ///
+    /// A slot holds its own reference to the file: closing the fd leaves it
+    /// open until the slot is emptied too.
+    fn register_files_update(&mut self, offset: u32, fds: Vec<RawFd>) -> PyResult<usize> {
+        let len = self
+            .file_table_len
+            .ok_or_else(|| PyValueError::new_err("No file table is registered"))?;
+        if offset as u64 + fds.len() as u64 > len as u64 {
+            return Err(PyValueError::new_err(format!(
+                "{} slots from {offset} don't fit the file table of {len}",
+                fds.len()
+            )));
+        }
+        self.uring()?
+            .submitter()
+            .register_files_update(offset, &fds)
+            .map_err(|e| RingError::new_err(format!("io_uring_register_files_update failed: {e}")))
+    }
+
+    /// Submit all queued SQEs to the kernel. Returns number submitted.
+    ///
+    /// On kernels with `IORING_FEAT_NODROP`, completions that don't fit the CQ
+    /// are held back rather than dropped. While any are, submitting raises
+    /// `CompletionQueueFull`: reap completions first. Older kernels refuse
+    /// the work with `EBUSY`; newer ones take it, so the ring checks itself.
+    ///
+    /// The GIL is released during the syscall, so other threads can run
+    /// while it blocks (e.g. waking an SQPOLL thread).
+    fn submit(&mut self, py: Python<'_>) -> PyResult<u32> {
+        if self.cq_backlogged()? {
+            return Err(CompletionQueueFull::new_err(
+                "Completion queue is full; drain completions first",
+            ));
+        }
+        let ring = self.uring_mut()?;
+        let n = py.detach(|| ring.submit()).map_err(|e| {
+            if e.raw_os_error() == Some(libc::EBUSY) && ring.params().is_feature_nodrop() {
+                CompletionQueueFull::new_err("Completion queue is full; drain completions first")
+            } else {
+                RingError::new_err(format!("io_uring_submit failed: {e}"))
+            }
+        })?;
+        Ok(n as u32)
+    }
+
+    /// `submit()` for a reactor loop: returns `(submitted, pending)`, with
+    /// `pending` the SQEs still in the SQ, rather than raising when the
+    /// kernel takes only some or none of them.
+    ///
+    /// `EBUSY` (a full CQ: drain completions, then retry), `EAGAIN` and
+    /// `EINTR` count as nothing submitted. Other errors still raise.
+    fn try_submit(&mut self, py: Python<'_>) -> PyResult<(u32, u32)> {
+        let backlogged = self.cq_backlogged()?;
+        let ring = self.uring_mut()?;
+        if backlogged {
+            return Ok((0, ring.sq_pending().0 as u32));
+        }
+        let submitted = match py.detach(|| ring.submit()) {
+            Ok(n) => n as u32,
+            Err(e)
+                if matches!(
+                    e.raw_os_error(),
+                    Some(libc::EBUSY | libc::EAGAIN | libc::EINTR)
+                ) =>
+            {
+                0
+            }
+            Err(e) => return Err(RingError::new_err(format!("io_uring_submit failed: {e}"))),
+        };
+        Ok((submitted, ring.sq_pending().0 as u32))
+    }
+
+    /// `(dropped, backlogged)` for the completion queue.
+    ///
+    /// The overflow policy is the kernel's and isn't configurable: CQEs are
+    /// never overwritten. With `IORING_FEAT_NODROP` (5.5+), completions that
+    /// don't fit are `backlogged` in the kernel and flushed as the CQ drains,
+    /// and submitting while they are raises `CompletionQueueFull`
+    /// (backpressure). `dropped` counts CQEs lost anyway: on older kernels,
+    /// or if the kernel can't allocate memory to hold one back. Reaping
+    /// warns when it goes up.
+    fn cq_overflow(&mut self) -> PyResult<(u32, bool)> {
+        Ok(self.uring_mut()?.cq_overflow())
+    }
+
+    /// Number of SQEs prepped but not yet consumed by the kernel: pushed
+    /// since the last `submit()`, or left over if it submitted only some.
+    ///
+    /// On an SQPOLL ring the poller thread consumes SQEs by itself. Ops in
+    /// an open `chain()` aren't pushed yet and don't count.
+    fn sq_pending(&mut self) -> PyResult<u32> {
+        Ok(self.uring_mut()?.sq_pending().0 as u32)
+    }
+
+    /// Hand queued SQEs to the SQPOLL thread without entering the kernel,
+    /// unless the thread has gone idle and needs waking.
+    ///
+    /// Returns the number of SQEs not yet consumed by the poller. On a ring
+    /// without SQPOLL this is the same as `submit()`.
+    fn submit_nonblocking(&mut self, py: Python<'_>) -> PyResult<u32> {
+        let ring = self.uring_mut()?;
+        let (pending, need_wakeup) = ring.sq_pending();
+        if ring.params().is_setup_sqpoll() && !need_wakeup {
+            return Ok(pending as u32);
+        }
+        self.submit(py)
+    }
+
+    /// Enter the kernel only to process completions, without submitting or
+    /// waiting. Returns the number of CQEs ready to reap.
+    ///
+    /// On a `defer_taskrun=True` ring, completions are only posted when the
+    /// submitting thread enters the kernel, so `peek()` alone may never see them.
+    /// The same goes for a `coop_taskrun=True` ring, which posts them on the
+    /// thread's next syscall instead of interrupting it.
+    fn get_events(&mut self) -> PyResult<u32> {
+        let ring = self.uring_mut()?;
+        // SAFETY: no SQEs are submitted and no argument is passed.
+        unsafe {
+            ring.submitter()
+                .enter::<libc::sigset_t>(0, 0, IORING_ENTER_GETEVENTS, None)
+        }
+        .map_err(|e| RingCompletionError::new_err(format!("io_uring_enter failed: {e}")))?;
+        Ok(ring.cq_len() as u32)
+    }
+
+    /// `(user_data, res, flags)` of up to `max` ready CQEs, oldest first,
+    /// without consuming them.
+    ///
+    /// Events already buffered by `wait_for()` are not included. Pair with
+    /// `advance_completions()` to consume what was inspected.
+    #[pyo3(signature = (max = None))]
+    fn completion_view(&mut self, max: Option<usize>) -> PyResult<Vec<(u64, i32, u32)>> {
+        let cqes = self
+            .uring_mut()?
+            .completions(max.unwrap_or(usize::MAX), false);
+        Ok(cqes
+            .iter()
+            .map(|(cqe, _)| (cqe.user_data(), cqe.result(), cqe.flags()))
+            .collect())
+    }
+
+    /// Consume up to `n` ready CQEs without building events for them, as a
+    /// fast path after inspecting them with `completion_view()`.
+    ///
+    /// Pins of finished ops stay held until `reconcile_completions()`
+    /// releases them all at once (prepping a new op under the same user_data
+    /// is still safe). Short sends of a `prep_socket_send_all` are resubmitted
+    /// as usual. Returns the number of CQEs consumed.
+    fn advance_completions(&mut self, n: usize) -> PyResult<usize> {
+        let cqes = self.uring_mut()?.completions(n, true);
+        for (cqe, _) in &cqes {
+            let user_data = cqe.user_data();
+            if self.continue_send_all(user_data, cqe.result()).is_none() {
+                continue;
+            }
+            if !cqueue::more(cqe.flags()) && user_data != WAKE_USER_DATA {
+                self.deferred_releases.push(user_data);
+                self.in_flight = self.in_flight.saturating_sub(1);
+            }
+        }
+        Ok(cqes.len())
+    }
+
+    /// Release the pins of every op consumed by `advance_completions()`.
+    /// Returns how many ops were reconciled.
+    fn reconcile_completions(&mut self) -> usize {
+        let user_datas = std::mem::take(&mut self.deferred_releases);
+        self.release_pinned_batch(&user_datas);
+        user_datas.len()
+    }
+
+    /// Consume ready completions into `out` without creating an event object
+    /// per completion, and return how many were written.
+    ///
+    /// `out` is a writable, C-contiguous buffer of 64-bit integers holding
+    /// rows of `(user_data, res, flags)`, such as an `array.array("q")` of
+    /// length `3 * n` or an `(n, 3)` numpy `int64` array; at most `n` rows are
+    /// filled, from offset 0. With a signed array, user_data with bit 63 set
+    /// reads back negative; with an unsigned one (`"Q"`, `uint64`), so does a
+    /// negative `res`. A provided buffer picked by a completion is only
+    /// reported through `flags`, as `flags >> 16`.
+    fn completions_into(&mut self, py: Python<'_>, out: &Bound<'_, PyAny>) -> PyResult<usize> {
+        if let Ok(buf) = PyBuffer::<i64>::get(out) {
+            self.fill_completions(py, &buf, |value| value as i64)
+        } else {
+            let buf = PyBuffer::<u64>::get(out)?;
+            self.fill_completions(py, &buf, |value| value)
+        }
+    }
+
+    /// Non-blocking peek.
+    ///
+    /// With `sync=True` (the default), a `defer_taskrun=True` ring first
+    /// enters the kernel to run deferred task work, so completions that have
+    /// finished but not yet been posted are visible. A `coop_taskrun=True`
+    /// ring only does so when the kernel flags pending task work
+    /// (`IORING_SQ_TASKRUN`). `sync=False` only reads what is already in the
+    /// completion queue, without a syscall.
+    #[pyo3(signature = (*, sync = true))]
+    fn peek(&mut self, sync: bool) -> PyResult<Option<CompletionEvent>> {
+        if self.completions.is_empty() {
+            if sync && (self.defer_taskrun || (self.coop_taskrun && self.uring_mut()?.taskrun())) {
+                self.get_events()?;
+            }
+            self.reap()?;
+        }
+        Ok(self.completions.pop_front())
+    }
+
+    /// Blocking wait for at least one CQE and return it.
+    ///
+    /// A signal runs its Python handler during the wait; if the handler
+    /// raises, so does `wait()`, e.g. `KeyboardInterrupt` on Ctrl-C.
+    ///
+    /// Raises `TimeoutError` if the `set_default_wait_timeout()` timeout
+    /// passes first; without one, it never does.
+    fn wait(&mut self, py: Python<'_>) -> PyResult<CompletionEvent> {
+        self.uring()?;
+        let Some(timeout) = self.wait_timeout else {
+            loop {
+                if let Some(event) = self.completions.pop_front() {
+                    return Ok(event);
+                }
+                self.submit_and_wait(py, 1)?;
+                self.reap()?;
+            }
+        };
+        let deadline = Some(Instant::now() + timeout);
+        self.flush_sq(py)?;
+        loop {
+            if let Some(event) = self.completions.pop_front() {
+                return Ok(event);
+            }
+            if !self.wait_cqe_until(py, deadline)? {
+                return Err(PyTimeoutError::new_err(format!(
+                    "No completion within the default wait timeout ({timeout:?})"
+                )));
+            }
+            self.reap()?;
+        }
+    }
+
+    /// Make `wait()` give up and raise `TimeoutError` after `sec`/`nsec`,
+    /// for an event loop with a fixed tick. `sec=None` makes it block until a CQE
+    /// arrives again, as by default.
+    #[pyo3(signature = (sec, nsec = 0))]
+    fn set_default_wait_timeout(&mut self, sec: Option<u64>, nsec: u32) {
+        self.wait_timeout = sec.map(|sec| Duration::new(sec, nsec));
+    }
+
+    /// Blocking wait for at least one CQE, then return every CQE ready.
+    ///
+    /// Completions buffered by `wait_for` come first, and if there are any
+    /// this doesn't block.
+    fn wait_and_drain(&mut self, py: Python<'_>) -> PyResult<Vec<CompletionEvent>> {
+        self.reap()?;
+        while self.completions.is_empty() {
+            self.submit_and_wait(py, 1)?;
+            self.reap()?;
+        }
+        Ok(self.completions.drain(..).collect())
+    }
+
+    /// Call `callback(user_data, res, flags)` for every ready CQE, without
+    /// creating a `CompletionEvent` for each, and return how many it handled.
+    /// It doesn't submit or block.
+    ///
+    /// Completions buffered by `wait_for` come first, and pins are released
+    /// as for `peek`. The callback may prep and submit more ops. If it
+    /// raises, the exception propagates and the unhandled completions stay
+    /// buffered for the next `peek`/`wait`.
+    fn for_each_completion(slf: &Bound<'_, Self>, callback: &Bound<'_, PyAny>) -> PyResult<usize> {
+        slf.borrow_mut().reap()?;
+        let mut handled = 0;
+        loop {
+            // Not borrowed across the call, so the callback can use the ring.
+            let Some(event) = slf.borrow_mut().completions.pop_front() else {
+                return Ok(handled);
+            };
+            callback.call1((event.user_data, event.res, event.flags))?;
+            handled += 1;
+        }
+    }
+
+    /// Submit all queued SQEs, wait until at least `min_complete` events are
+    /// ready, and return every ready event, in one call.
+    ///
+    /// Completions buffered by `wait_for` come first and count towards
+    /// `min_complete`. The GIL is released while waiting.
+    #[pyo3(signature = (min_complete = 1))]
+    fn submit_wait_drain(
+        &mut self,
+        py: Python<'_>,
+        min_complete: usize,
+    ) -> PyResult<Vec<CompletionEvent>> {
+        self.reap()?;
+        loop {
+            let want = min_complete.saturating_sub(self.completions.len());
+            self.submit_and_wait(py, want)?;
+            self.reap()?;
+            if self.completions.len() >= min_complete {
+                return Ok(self.completions.drain(..).collect());
+            }
+        }
+    }
+
+    /// Submit, then wait until `count` events are ready or the absolute
+    /// deadline `deadline_sec`/`deadline_nsec` on `clock` passes, and return
+    /// every ready event: fewer than `count` if the deadline hit first.
+    ///
+    /// `clock` is as for `prep_timeout`, so `CLOCK_REALTIME` takes a
+    /// wall-clock deadline. Both ends of the wait are one `io_uring_enter`:
+    /// an internal `IORING_TIMEOUT_ABS` timeout, which also completes after
+    /// `count` other CQEs, ends the wait either way. Completions buffered by
+    /// `wait_for` come first and count towards `count`.
+    #[pyo3(signature = (count, deadline_sec, deadline_nsec, *, clock = libc::CLOCK_MONOTONIC))]
+    fn wait_until(
+        &mut self,
+        py: Python<'_>,
+        count: u32,
+        deadline_sec: u64,
+        deadline_nsec: u32,
+        clock: libc::clockid_t,
+    ) -> PyResult<Vec<CompletionEvent>> {
+        let flags = timeout_clock_flags(clock)? | types::TimeoutFlags::ABS;
+        self.reap()?;
+        let want = (count as usize).saturating_sub(self.completions.len());
+        if want > 0 {
+            let ts = Box::new(types::Timespec::new().sec(deadline_sec).nsec(deadline_nsec));
+            let entry = opcode::Timeout::new(&*ts)
+                .count(want as u32)
+                .flags(flags)
+                .build()
+                .user_data(WAIT_UNTIL_USER_DATA);
+            self.pin(WAIT_UNTIL_USER_DATA, Pinned::Timespec(ts));
+            // Straight onto the SQ, even inside a `chain()`.
+            self.push_entries_unchecked(&[entry])?;
+            // Any timeout CQE wakes the wait, whatever `want` is.
+            self.submit_and_wait(py, want)?;
+            self.reap()?;
+        }
+        Ok(self.completions.drain(..).collect())
+    }
+
+    /// Coroutine: submit, then wait for completions without blocking the
+    /// running asyncio loop, and return every ready event.
+    ///
+    /// Waits on `register_eventfd()`'s fd, watched with `loop.add_reader`
+    /// only while there's nothing to return. See `rusty_ring._asyncio`.
+    fn wait_async<'py>(slf: Bound<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let wait_async = slf
+            .py()
+            .import("rusty_ring._asyncio")?
+            .getattr("wait_async")?;
+        wait_async.call1((slf,))
+    }
+
+    /// Block until the op with `user_data` completes and return its event.
+    ///
+    /// Other completions reaped meanwhile are kept, in order, and returned
+    /// by later `peek`/`wait`/`wait_and_drain` calls. Returns `None` if
+    /// `timeout` seconds pass first.
+    #[pyo3(signature = (user_data, timeout = None))]
+    fn wait_for(
+        &mut self,
+        py: Python<'_>,
+        user_data: u64,
+        timeout: Option<f64>,
+    ) -> PyResult<Option<CompletionEvent>> {
+        let deadline = deadline_after(timeout);
+        loop {
+            self.reap()?;
+            if let Some(pos) = self
+                .completions
+                .iter()
+                .position(|event| event.user_data == user_data)
+            {
+                return Ok(self.completions.remove(pos));
+            }
+            if !self.wait_cqe_until(py, deadline)? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Shut the ring down once everything in flight has completed.
+    ///
+    /// Refuses further preps, then reaps CQEs (multishot ones included)
+    /// until `in_flight` drops to zero, and closes the ring. With
+    /// `cancel=True` every in-flight op is cancelled first rather than
+    /// waited on. Returns the reaped events.
+    ///
+    /// If `timeout` seconds pass with ops still in flight, this returns
+    /// early and leaves the ring open, since closing it would release
+    /// buffers the kernel may still write to. Check `in_flight`, then call
+    /// again or exit the context manager.
+    #[pyo3(signature = (timeout = None, *, cancel = false))]
+    fn shutdown(
+        &mut self,
+        py: Python<'_>,
+        timeout: Option<f64>,
+        cancel: bool,
+    ) -> PyResult<Vec<CompletionEvent>> {
+        let deadline = deadline_after(timeout);
+        if cancel && self.in_flight > 0 && !self.shutting_down {
+            let entry = opcode::AsyncCancel2::new(types::CancelBuilder::any())
+                .build()
+                .user_data(SHUTDOWN_CANCEL_USER_DATA);
+            self.push_own_entries(&[entry])?;
+        }
+        self.uring()?;
+        self.shutting_down = true;
+
+        let mut events = Vec::new();
+        loop {
+            self.reap()?;
+            events.extend(self.completions.drain(..));
+            if self.in_flight == 0 {
+                break;
+            }
+            if !self.wait_cqe_until(py, deadline)? {
+                return Ok(events);
+            }
+        }
+        self.close();
+        Ok(events)
+    }
+
+    /// Push a prebuilt `io_uring_sqe`, copied verbatim, for opcodes that have
+    /// no `prep_*` method yet. That's 64 bytes, or 128 on an `sqe128=True`
+    /// ring, whose ops can take either.
+    ///
+    /// **Unsafe:** nothing about the SQE is checked, and nothing it points to
+    /// is pinned. Any buffer, path or struct it references must stay alive
+    /// and unmoved until its CQE arrives, or the kernel will read or write
+    /// freed memory. Its user_data must not clash with other in-flight ops.
+    fn unsafe_push_raw_sqe(&mut self, sqe: &[u8]) -> PyResult<()> {
+        if let Ok(raw) = <[u8; 128]>::try_from(sqe) {
+            // SAFETY: `squeue::Entry128` wraps a plain 128-byte SQE; what the
+            // bytes ask the kernel to do is the caller's responsibility.
+            let entry: squeue::Entry128 = unsafe { std::mem::transmute(raw) };
+            return self.push_wide_entry(entry);
+        }
+        let raw: [u8; 64] = sqe.try_into().map_err(|_| {
+            PyValueError::new_err(format!("An SQE is 64 or 128 bytes, got {}", sqe.len()))
+        })?;
+        // SAFETY: `squeue::Entry` wraps a plain 64-byte `io_uring_sqe`; what
+        // the bytes ask the kernel to do is the caller's responsibility.
+        let entry: squeue::Entry = unsafe { std::mem::transmute(raw) };
+        self.push_own_entries(&[entry])
+    }
+
+    /// Open a chain: ops prepped inside `with ring.chain() as c:` are linked
+    /// in order (`IO_LINK` on all but the last) and pushed together, or not
+    /// at all, when the block exits. They're dropped if the block raises.
+    ///
+    /// `c` is this ring. Nothing is submitted until the block exits, and a
+    /// chain can't be nested in another.
+    fn chain(slf: PyRef<'_, Self>) -> Chain {
+        Chain { ring: slf.into() }
+    }
+
+    /// Submit a no-op.
+    #[pyo3(signature = (user_data, *, cookie = None))]
+    fn prep_nop(&mut self, user_data: u64, cookie: Option<Py<PyAny>>) -> PyResult<()> {
+        let entry = opcode::Nop::new().build().user_data(user_data);
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Submit a timeout (sleep).
+    ///
+    /// `clock` is a `time.CLOCK_*` id: `CLOCK_MONOTONIC`, `CLOCK_BOOTTIME`
+    /// (keeps counting across suspend) or `CLOCK_REALTIME`. With
+    /// `absolute=True`, `sec`/`nsec` is a deadline on that clock, e.g. a
+    /// wall-clock time for `CLOCK_REALTIME`.
+    #[pyo3(signature = (
+        user_data,
+        sec,
+        nsec,
+        *,
+        clock = libc::CLOCK_MONOTONIC,
+        absolute = false,
+        cookie = None,
+    ))]
+    fn prep_timeout(
+        &mut self,
+        user_data: u64,
+        sec: u64,
+        nsec: u32,
+        clock: libc::clockid_t,
+        absolute: bool,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        let mut flags = timeout_clock_flags(clock)?;
+        if absolute {
+            flags |= types::TimeoutFlags::ABS;
+        }
+        let ts = Box::new(types::Timespec::new().sec(sec).nsec(nsec));
+        let entry = opcode::Timeout::new(&*ts)
+            .flags(flags)
+            .build()
+            .user_data(user_data);
+
+        self.pin(user_data, Pinned::Timespec(ts));
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a batch of timeouts, one per `(user_data, sec, nsec, flags)` in
+    /// `specs`, as for `prep_timeout`.
+    ///
+    /// `flags` takes `IORING_TIMEOUT_ABS` and one clock,
+    /// `IORING_TIMEOUT_BOOTTIME` or `IORING_TIMEOUT_REALTIME` (the default is
+    /// `CLOCK_MONOTONIC`). With `IORING_TIMEOUT_ETIME_SUCCESS`, expiry
+    /// (`-ETIME`) doesn't fail a link, so ops linked after it still run. All
+    /// are pushed, or none (raising `SubmissionQueueFull`), so a batch can't
+    /// exceed the free SQ slots.
+    fn prep_timeouts(&mut self, specs: Vec<(u64, u64, u32, u32)>) -> PyResult<()> {
+        let mut user_datas = Vec::with_capacity(specs.len());
+        let mut entries = Vec::with_capacity(specs.len());
+        let mut timespecs = Vec::with_capacity(specs.len());
+        for (user_data, sec, nsec, flags) in specs {
+            let flags = types::TimeoutFlags::from_bits(flags)
+                .filter(|flags| TIMEOUTS_FLAGS.contains(*flags))
+                .ok_or_else(|| {
+                    PyValueError::new_err(format!(
+                        "Invalid timeout flags {flags:#x} for user_data {user_data}"
+                    ))
+                })?;
+            let ts = Box::new(types::Timespec::new().sec(sec).nsec(nsec));
+            entries.push(
+                opcode::Timeout::new(&*ts)
+                    .flags(flags)
+                    .build()
+                    .user_data(user_data),
+            );
+            user_datas.push(user_data);
+            timespecs.push(ts);
+        }
+
+        self.pinned.reserve(user_datas.len());
+        for (&user_data, ts) in user_datas.iter().zip(timespecs) {
+            self.pin(user_data, Pinned::Timespec(ts));
+        }
+        self.push_entries(&entries)
+            .inspect_err(|_| self.release_pinned_batch(&user_datas))?;
+        for &user_data in &user_datas {
+            self.set_cookie(user_data, None);
+        }
+        Ok(())
+    }
+
+    /// Prep a read into `buf`.
+    /// The `buf` (a Python `bytearray`) is pinned until the CQE is consumed.
+    /// **Do not resize `buf` between prep and consuming the CQE.**
+    ///
+    /// `buf_offset` reads into `buf` from that index on, so concurrent reads
+    /// can fill disjoint regions of one bytearray (which is pinned once).
+    ///
+    /// `offset=-1` reads from (and advances) the current file position, as
+    /// needed for pipes and other non-seekable files.
+    /// `ioprio` is passed to the block layer, see `ioprio_value()`.
+    /// `rw_flags` takes `RWF_*` flags as for `preadv2(2)`, e.g. `RWF_NOWAIT`
+    /// to get `-EAGAIN` instead of blocking on uncached data.
+    #[pyo3(signature = (
+        user_data,
+        fd,
+        buf,
+        nbytes,
+        offset,
+        *,
+        buf_offset = 0,
+        ioprio = 0,
+        rw_flags = 0,
+        cookie = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn prep_read(
+        &mut self,
+        _py: Python<'_>,
+        user_data: u64,
+        fd: RawFd,
+        buf: Bound<'_, PyByteArray>,
+        nbytes: u32,
+        offset: i64,
+        buf_offset: usize,
+        ioprio: u16,
+        rw_flags: i32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_read", fd)?;
+        if buf_offset > buf.len() {
+            return Err(PyValueError::new_err(format!(
+                "buf_offset {buf_offset} is past the end of a {}-byte buffer",
+                buf.len()
+            )));
+        }
+        // SAFETY: `buf_offset` is within the buffer.
+        let ptr = unsafe { buf.data().add(buf_offset) };
+        let len = nbytes.min((buf.len() - buf_offset) as u32);
+
+        let entry = opcode::Read::new(types::Fd(fd), ptr.cast(), len)
+            .offset(file_offset(offset)?)
+            .ioprio(ioprio)
+            .rw_flags(rw_flags)
+            .build()
+            .user_data(user_data);
+
+        self.pin_mutable(user_data, buf);
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a multishot read (6.7+) from `fd`, drawing a buffer from
+    /// provided-buffer group `group_id` for each chunk that arrives.
+    ///
+    /// `fd` must be pollable, e.g. a pipe, socket or character device; other
+    /// files fail with `-EBADFD`. Each chunk posts a CQE with `buffer_ids`
+    /// set, and the op stays live while `IORING_CQE_F_MORE` is set. It ends
+    /// at EOF (`res == 0`), on an error, or when the group runs out of
+    /// buffers (`out_of_buffers`); prep it again to keep reading.
+    #[pyo3(signature = (user_data, fd, group_id, *, cookie = None))]
+    fn prep_read_multishot(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        group_id: u16,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_read_multishot", fd)?;
+        self.buf_ring(group_id)?;
+        let entry = opcode::ReadMulti::new(types::Fd(fd), 0, group_id)
+            .build()
+            .user_data(user_data);
+
+        self.buffer_groups.insert(user_data, (group_id, false));
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a read of up to `nbytes` into registered buffer `buf_index`.
+    ///
+    /// Take `buf_index` from `BufferPool.acquire(user_data)`.
+    #[pyo3(signature = (user_data, fd, buf_index, nbytes, offset, *, cookie = None))]
+    fn prep_read_fixed(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        buf_index: u16,
+        nbytes: u32,
+        offset: i64,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_read_fixed", fd)?;
+        let pool = self
+            .buffer_pool
+            .as_ref()
+            .ok_or_else(|| PyValueError::new_err("No buffer pool is registered"))?;
+        let ptr = pool.get().buffer_ptr(buf_index, 0, nbytes)?;
+
+        let entry = opcode::ReadFixed::new(types::Fd(fd), ptr, nbytes, buf_index)
+            .offset(file_offset(offset)?)
+            .build()
+            .user_data(user_data);
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a write of `nbytes` from registered buffer `buf_index`, starting
+    /// `buf_offset` bytes into it, e.g. after `BufferPool.write`.
+    ///
+    /// The range must fit the buffer. Take `buf_index` from
+    /// `BufferPool.acquire(user_data)`. `write_hint` is set on the file as
+    /// for `prep_write`.
+    #[pyo3(signature = (
+        user_data,
+        fd,
+        buf_index,
+        nbytes,
+        offset,
+        *,
+        buf_offset = 0,
+        write_hint = None,
+        cookie = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn prep_write_fixed(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        buf_index: u16,
+        nbytes: u32,
+        offset: i64,
+        buf_offset: u32,
+        write_hint: Option<u64>,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_write_fixed", fd)?;
+        let pool = self
+            .buffer_pool
+            .as_ref()
+            .ok_or_else(|| PyValueError::new_err("No buffer pool is registered"))?;
+        let ptr = pool.get().buffer_ptr(buf_index, buf_offset, nbytes)?;
+        if let Some(hint) = write_hint {
+            set_write_hint(fd, hint)?;
+        }
+
+        let entry = opcode::WriteFixed::new(types::Fd(fd), ptr, nbytes, buf_index)
+            .offset(file_offset(offset)?)
+            .build()
+            .user_data(user_data);
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    // Prepares statx for metadata extraction.
+    #[pyo3(signature = (user_data, path, buf, flags, mask, dir_fd, *, cookie = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn prep_statx(
+        &mut self,
+        user_data: u64,
+        path: &str,
+        buf: Bound<'_, StatxBuffer>,
+        flags: i32,
+        mask: u32,
+        dir_fd: RawFd,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        if dir_fd != libc::AT_FDCWD {
+            check_fd("prep_statx", dir_fd)?;
+        }
+        let c_path =
+            CString::new(path).map_err(|_| PyValueError::new_err("Path contains null byte"))?;
+        let path_ptr = c_path.as_ptr();
+
+        let mut guard = buf.borrow_mut();
+        let statxbuf_ptr = &mut *guard.inner as *mut libc::statx as *mut io_uring::types::statx;
+
+        let entry = opcode::Statx::new(types::Fd(dir_fd), path_ptr, statxbuf_ptr)
+            .flags(flags)
+            .mask(mask)
+            .build()
+            .user_data(user_data);
+
+        drop(guard);
+
+        self.pin(
+            user_data,
+            Pinned::Statx(StatxRequest {
+                path: c_path,
+                statxbuf: buf.unbind(),
+            }),
+        );
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a read of extended attribute `name` (e.g. `"user.sha256"`) of
+    /// the file at `path` into `value`.
+    ///
+    /// `res` is the value's length, or `-ERANGE` if it doesn't fit `value`
+    /// (`-ENODATA` if the file has no such attribute). An empty `value`
+    /// only asks for the length. Symlinks are followed. Needs 5.19+.
+    #[pyo3(signature = (user_data, path, name, value, *, cookie = None))]
+    fn prep_getxattr(
+        &mut self,
+        user_data: u64,
+        path: &str,
+        name: &str,
+        value: Bound<'_, PyByteArray>,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        let path =
+            CString::new(path).map_err(|_| PyValueError::new_err("Path contains null byte"))?;
+        let name = xattr_name(name)?;
+        let len = xattr_len(value.len())?;
+
+        let entry = opcode::GetXattr::new(name.as_ptr(), value.data().cast(), path.as_ptr(), len)
+            .build()
+            .user_data(user_data);
+
+        self.pin(
+            user_data,
+            Pinned::Xattr(XattrRequest {
+                path: Some(path),
+                name,
+                value: value.into_any().unbind(),
+            }),
+        );
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep setting extended attribute `name` of the file at `path` to
+    /// `value`.
+    ///
+    /// `flags` takes `XATTR_CREATE` (fail with `-EEXIST` if it's set) or
+    /// `XATTR_REPLACE` (fail with `-ENODATA` if it isn't); by default it is
+    /// set either way. Symlinks are followed. Needs 5.19+.
+    #[pyo3(signature = (user_data, path, name, value, flags = 0, *, cookie = None))]
+    fn prep_setxattr(
+        &mut self,
+        user_data: u64,
+        path: &str,
+        name: &str,
+        value: Bound<'_, PyBytes>,
+        flags: i32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        let path =
+            CString::new(path).map_err(|_| PyValueError::new_err("Path contains null byte"))?;
+        let name = xattr_name(name)?;
+        let data = value.as_bytes();
+        let len = xattr_len(data.len())?;
+
+        let entry = opcode::SetXattr::new(name.as_ptr(), data.as_ptr().cast(), path.as_ptr(), len)
+            .flags(flags)
+            .build()
+            .user_data(user_data);
+
+        self.pin(
+            user_data,
+            Pinned::Xattr(XattrRequest {
+                path: Some(path),
+                name,
+                value: value.into_any().unbind(),
+            }),
+        );
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// `prep_getxattr` on the open file `fd`, without a path lookup.
+    #[pyo3(signature = (user_data, fd, name, value, *, cookie = None))]
+    fn prep_fgetxattr(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        name: &str,
+        value: Bound<'_, PyByteArray>,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_fgetxattr", fd)?;
+        let name = xattr_name(name)?;
+        let len = xattr_len(value.len())?;
+
+        let entry = opcode::FGetXattr::new(types::Fd(fd), name.as_ptr(), value.data().cast(), len)
+            .build()
+            .user_data(user_data);
+
+        self.pin(
+            user_data,
+            Pinned::Xattr(XattrRequest {
+                path: None,
+                name,
+                value: value.into_any().unbind(),
+            }),
+        );
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// `prep_setxattr` on the open file `fd`, without a path lookup.
+    #[pyo3(signature = (user_data, fd, name, value, flags = 0, *, cookie = None))]
+    fn prep_fsetxattr(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        name: &str,
+        value: Bound<'_, PyBytes>,
+        flags: i32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_fsetxattr", fd)?;
+        let name = xattr_name(name)?;
+        let data = value.as_bytes();
+        let len = xattr_len(data.len())?;
+
+        let entry = opcode::FSetXattr::new(types::Fd(fd), name.as_ptr(), data.as_ptr().cast(), len)
+            .flags(flags)
+            .build()
+            .user_data(user_data);
+
+        self.pin(
+            user_data,
+            Pinned::Xattr(XattrRequest {
+                path: None,
+                name,
+                value: value.into_any().unbind(),
+            }),
+        );
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a file write.
+    ///
+    /// `offset=-1` writes at (and advances) the current file position.
+    /// `ioprio` is passed to the block layer, see `ioprio_value()`.
+    /// `rw_flags` takes `RWF_*` flags as for `pwritev2(2)`, e.g. `RWF_DSYNC`.
+    ///
+    /// `append=True` adds `RWF_APPEND`, so this write alone lands atomically
+    /// at the end of the file, as if the fd was opened with `O_APPEND`. On
+    /// an appending write (either way) `offset` is ignored, but `-1` also
+    /// moves the file position past the new data.
+    ///
+    /// `write_hint` takes a `RWH_WRITE_LIFE_*` lifetime for the data. The
+    /// kernel has no per-write field for it, so it is set right away on the
+    /// file itself with `fcntl(F_SET_RW_HINT)` and also applies to later
+    /// writes to it, through any fd. Filesystems without support ignore it.
+    #[pyo3(signature = (
+        user_data,
+        fd,
+        buf,
+        offset,
+        *,
+        ioprio = 0,
+        rw_flags = 0,
+        write_hint = None,
+        append = false,
+        cookie = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn prep_write(
+        &mut self,
+        _py: Python<'_>,
+        user_data: u64,
+        fd: RawFd,
+        buf: Bound<'_, PyBytes>,
+        offset: i64,
+        ioprio: u16,
+        rw_flags: i32,
+        write_hint: Option<u64>,
+        append: bool,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_write", fd)?;
+        let rw_flags = if append {
+            rw_flags | libc::RWF_APPEND
+        } else {
+            rw_flags
+        };
+        if let Some(hint) = write_hint {
+            set_write_hint(fd, hint)?;
+        }
+        let data = buf.as_bytes();
+        let ptr = data.as_ptr();
+        let len = data.len() as u32;
+
+        let entry = opcode::Write::new(types::Fd(fd), ptr.cast(), len)
+            .offset(file_offset(offset)?)
+            .ioprio(ioprio)
+            .rw_flags(rw_flags)
+            .build()
+            .user_data(user_data);
+
+        self.pin(user_data, Pinned::ImmutableBuffer(buf.unbind()));
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a file open.
+    ///
+    /// `flags` takes the `O_*` constants. `mode` holds the permission bits
+    /// (e.g. `0o644`) of a file created with `O_CREAT` or `O_TMPFILE`, and is
+    /// ignored otherwise (with a warning if non-zero).
+    /// A relative `path` resolves against `dir_fd`, by default the current
+    /// working directory (`AT_FDCWD`).
+    ///
+    /// Pass a `personality` id from `register_personality()` to open with
+    /// the credentials registered under it.
+    #[pyo3(signature = (
+        user_data,
+        path,
+        flags,
+        mode = 0,
+        dir_fd = libc::AT_FDCWD,
+        *,
+        personality = None,
+        cookie = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn prep_openat(
+        &mut self,
+        py: Python<'_>,
+        user_data: u64,
+        path: &str,
+        flags: i32,
+        mode: u32,
+        dir_fd: RawFd,
+        personality: Option<u16>,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        if dir_fd != libc::AT_FDCWD {
+            check_fd("prep_openat", dir_fd)?;
+        }
+        if mode & !0o7777 != 0 {
+            return Err(PyValueError::new_err(format!(
+                "Invalid mode {mode:#o}: only permission bits (0o7777) are allowed"
+            )));
+        }
+        let creates = flags & libc::O_CREAT != 0 || flags & libc::O_TMPFILE == libc::O_TMPFILE;
+        if mode != 0 && !creates {
+            PyErr::warn(
+                py,
+                &py.get_type::<PyUserWarning>(),
+                c"mode is ignored without O_CREAT or O_TMPFILE",
+                1,
+            )?;
+        }
+        let c_path =
+            CString::new(path).map_err(|_| PyValueError::new_err("Path contains null byte"))?;
+        let ptr = c_path.as_ptr();
+
+        let mut entry = opcode::OpenAt::new(types::Fd(dir_fd), ptr)
+            .flags(flags)
+            .mode(mode)
+            .build()
+            .user_data(user_data);
+        if let Some(id) = personality {
+            entry = entry.personality(id);
+        }
+
+        // Pin the CString so the pointer stays valid until CQE
+        self.pin(user_data, Pinned::Path(c_path));
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a poll for `mask` events (`POLLIN`, `POLLOUT`, ...) on `fd`.
+    ///
+    /// The CQE `res` is the mask of events that fired. With `multishot=True`
+    /// the poll re-arms itself and posts a CQE each time `fd` becomes ready,
+    /// flagged `IORING_CQE_F_MORE` while it stays armed; cancel it with
+    /// `prep_cancel` when done. The kernel makes a multishot poll on another
+    /// ring's fd one-shot; see `prep_poll_ring`.
+    #[pyo3(signature = (user_data, fd, mask, *, multishot = false, cookie = None))]
+    fn prep_poll_add(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        mask: u32,
+        multishot: bool,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_poll_add", fd)?;
+        let entry = opcode::PollAdd::new(types::Fd(fd), mask)
+            .multi(multishot)
+            .build()
+            .user_data(user_data);
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a poll that completes (`res` is `POLLIN`) once `ring`, a child
+    /// of this one, has CQEs to reap; for a ring of rings.
+    ///
+    /// It's one-shot: the kernel doesn't multishot-poll ring fds. After it
+    /// fires, drain `ring` and prep it again. If CQEs came in meanwhile, the
+    /// new poll fires straight away, so none go unnoticed.
+    #[pyo3(signature = (user_data, ring, *, cookie = None))]
+    fn prep_poll_ring(
+        &mut self,
+        user_data: u64,
+        ring: &Bound<'_, Ring>,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        let fd = ring
+            .try_borrow()
+            .map_err(|_| PyValueError::new_err("A ring can't poll itself"))?
+            .fileno()?;
+        self.prep_poll_add(user_data, fd, libc::POLLIN as u32, false, cookie)
+    }
+
+    /// Prep an in-place update of the poll submitted as `target_user_data`.
+    ///
+    /// `new_mask` replaces the events it watches and `new_user_data` the
+    /// user_data its later CQEs carry; `None` keeps either as is. Pass
+    /// `multishot=True` with a `new_mask` to keep a multishot poll re-arming
+    /// after the update (the kernel reads it as part of the new mask).
+    /// The update's own `res` is 0, or `-ENOENT` if no such poll is armed.
+    #[pyo3(signature = (
+        user_data,
+        target_user_data,
+        new_mask = None,
+        *,
+        new_user_data = None,
+        multishot = false,
+        cookie = None,
+    ))]
+    fn prep_poll_update(
+        &mut self,
+        user_data: u64,
+        target_user_data: u64,
+        new_mask: Option<u32>,
+        new_user_data: Option<u64>,
+        multishot: bool,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        if multishot && new_mask.is_none() {
+            return Err(PyValueError::new_err(
+                "multishot=True only applies together with new_mask",
+            ));
+        }
+        let mut flags = 0;
+        if multishot {
+            flags |= IORING_POLL_ADD_MULTI;
+        }
+        if new_mask.is_some() {
+            flags |= IORING_POLL_UPDATE_EVENTS;
+        }
+        if let Some(new_user_data) = new_user_data {
+            check_user_data(new_user_data)?;
+            flags |= IORING_POLL_UPDATE_USER_DATA;
+        }
+        let entry = opcode::PollRemove::new(target_user_data)
+            .build()
+            .user_data(user_data);
+        let entry = patch_sqe(entry, |sqe| {
+            sqe.len = flags;
+            sqe.off = new_user_data.unwrap_or(0);
+            sqe.op_flags = new_mask.unwrap_or(0);
+        });
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep an `IORING_OP_URING_CMD`: command `cmd_op` for the driver behind
+    /// `fd`, e.g. NVMe passthrough or a `SOCKET_URING_OP_*` socket command.
+    ///
+    /// `cmd_buf` is the command payload, copied into the SQE: up to 16 bytes
+    /// on any ring, up to 80 on an `sqe128=True` ring (which NVMe needs).
+    /// Memory the payload points to (e.g. an NVMe command's data buffer) is
+    /// not pinned and must outlive the op.
+    #[pyo3(signature = (user_data, fd, cmd_op, cmd_buf = b"".as_slice(), *, cookie = None))]
+    fn prep_uring_cmd(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        cmd_op: u32,
+        cmd_buf: &[u8],
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_uring_cmd", fd)?;
+        check_user_data(user_data)?;
+        if cmd_buf.len() <= 16 {
+            let mut cmd = [0u8; 16];
+            cmd[..cmd_buf.len()].copy_from_slice(cmd_buf);
+            let entry = opcode::UringCmd16::new(types::Fd(fd), cmd_op)
+                .cmd(cmd)
+                .build()
+                .user_data(user_data);
+            return self
+                .push_entry(entry)
+                .inspect(|_| self.set_cookie(user_data, cookie));
+        }
+        if cmd_buf.len() > 80 {
+            return Err(PyValueError::new_err(format!(
+                "A command payload is at most 80 bytes, got {}",
+                cmd_buf.len()
+            )));
+        }
+        if !self.sqe128 {
+            return Err(PyValueError::new_err(
+                "Command payloads over 16 bytes need a Ring(sqe128=True)",
+            ));
+        }
+        let mut cmd = [0u8; 80];
+        cmd[..cmd_buf.len()].copy_from_slice(cmd_buf);
+        let entry = opcode::UringCmd80::new(types::Fd(fd), cmd_op)
+            .cmd(cmd)
+            .build()
+            .user_data(user_data);
+        self.push_wide_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a wait for the process behind `pidfd` to exit.
+    ///
+    /// Completes when the pidfd becomes readable, i.e. the child has exited.
+    /// This doesn't reap it: follow up with
+    /// `os.waitid(os.P_PIDFD, pidfd, os.WEXITED)`, which then returns without
+    /// blocking, to collect the exit status and avoid a zombie.
+    #[pyo3(signature = (user_data, pidfd, *, cookie = None))]
+    fn prep_pidfd_wait(
+        &mut self,
+        user_data: u64,
+        pidfd: RawFd,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_pidfd_wait", pidfd)?;
+        self.prep_poll_add(user_data, pidfd, libc::POLLIN as u32, false, cookie)
+    }
+
+    /// Prep a `posix_fadvise(2)` of `advice` (`POSIX_FADV_*`) for `length`
+    /// bytes of `fd` from `offset` (0 meaning to the end of the file).
+    ///
+    /// `res` is the syscall's return, unmodified: 0 means the advice was
+    /// valid and accepted, not that it changed anything (most of it is a
+    /// best-effort hint). Errors are `-EINVAL` for unknown advice or a bad
+    /// range, `-ESPIPE` for a pipe or FIFO and `-EBADF` for a bad fd.
+    #[pyo3(signature = (user_data, fd, offset, length, advice, *, cookie = None))]
+    fn prep_fadvise(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        offset: u64,
+        length: i64,
+        advice: i32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_fadvise", fd)?;
+        let entry = opcode::Fadvise::new(types::Fd(fd), length, advice)
+            .offset(offset)
+            .build()
+            .user_data(user_data);
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a `madvise(2)` of `advice` (`MADV_*`) for the memory of `buf`
+    /// (typically an `mmap.mmap`), from `offset` for `length` bytes (by
+    /// default the rest of it). The start must be page-aligned.
+    ///
+    /// `buf` is held until the CQE, so it can't be closed or resized under
+    /// the op. As with `prep_fadvise`, `res` is the syscall's return: 0 for
+    /// accepted advice, `-EINVAL` for unknown advice or an unaligned start.
+    #[pyo3(signature = (user_data, buf, advice, offset = 0, length = None, *, cookie = None))]
+    fn prep_madvise(
+        &mut self,
+        user_data: u64,
+        buf: &Bound<'_, PyAny>,
+        advice: i32,
+        offset: usize,
+        length: Option<usize>,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        let view = PyBuffer::<u8>::get(buf)?;
+        let available = view.len_bytes().saturating_sub(offset);
+        let length = length.unwrap_or(available);
+        if offset > view.len_bytes() || length > available {
+            return Err(PyValueError::new_err(format!(
+                "Range {offset}+{length} is out of bounds for a {}-byte buffer",
+                view.len_bytes()
+            )));
+        }
+        let addr = (view.buf_ptr() as usize + offset) as *const libc::c_void;
+        let entry = opcode::Madvise::new(addr, length as libc::off_t, advice)
+            .build()
+            .user_data(user_data);
+
+        self.pin(user_data, Pinned::BufferView(view));
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep an `fsync(2)` of `fd`, or an `fdatasync(2)` with `datasync=True`
+    /// (skips metadata such as timestamps that isn't needed to read the data
+    /// back). Either flushes the whole file; see `prep_sync_file_range` for
+    /// a byte range.
+    #[pyo3(signature = (user_data, fd, *, datasync = false, cookie = None))]
+    fn prep_fsync(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        datasync: bool,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_fsync", fd)?;
+        let flags = if datasync {
+            types::FsyncFlags::DATASYNC
+        } else {
+            types::FsyncFlags::empty()
+        };
+        let entry = opcode::Fsync::new(types::Fd(fd))
+            .flags(flags)
+            .build()
+            .user_data(user_data);
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a `sync_file_range(2)` of `nbytes` bytes of `fd` from `offset`
+    /// (`nbytes=0` means to the end of the file), with `flags` from
+    /// `SYNC_FILE_RANGE_*`.
+    ///
+    /// Unlike `prep_fsync` this only starts and/or waits on writeback of the
+    /// data pages: it flushes no metadata and no disk cache, so it is no
+    /// durability guarantee on its own. Use it to push out the appended
+    /// range early and cheaply, then `prep_fsync(datasync=True)` when the
+    /// data must survive a crash:
+    /// ```python
+    ///     with ring.chain():
+    ///         ring.prep_write(1, fd, record, offset)
+    ///         ring.prep_sync_file_range(2, fd, offset, len(record),
+    ///                                   SYNC_FILE_RANGE_WRITE)
+    ///     ...
+    ///     ring.prep_sync_file_range(3, fd, offset, len(record),
+    ///                               SYNC_FILE_RANGE_WAIT_AFTER)
+    /// ```
+    #[pyo3(signature = (user_data, fd, offset = 0, nbytes = 0, flags = 0, *, cookie = None))]
+    fn prep_sync_file_range(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        offset: u64,
+        nbytes: u32,
+        flags: u32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_sync_file_range", fd)?;
+        let entry = opcode::SyncFileRange::new(types::Fd(fd), nbytes)
+            .offset(offset)
+            .flags(flags)
+            .build()
+            .user_data(user_data);
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a file/socket close of `fd`, or of file table slot `file_index`,
+    /// which empties the slot. Give exactly one of them.
+    ///
+    /// A registered fd is only released once both it and its slot are
+    /// closed, e.g. with two `prep_close`s in a `chain()`.
+    #[pyo3(signature = (user_data, fd = None, *, file_index = None, cookie = None))]
+    fn prep_close(
+        &mut self,
+        user_data: u64,
+        fd: Option<RawFd>,
+        file_index: Option<u32>,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        let entry = match (fd, file_index) {
+            (Some(fd), None) => {
+                check_fd("prep_close", fd)?;
+                opcode::Close::new(types::Fd(fd)).build()
+            }
+            (None, Some(index)) => {
+                if self.file_table_len.is_none_or(|len| index >= len) {
+                    return Err(PyValueError::new_err(format!(
+                        "file_index {index} is not a slot of the registered file table"
+                    )));
+                }
+                opcode::Close::new(types::Fixed(index)).build()
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "prep_close takes exactly one of fd and file_index",
+                ));
+            }
+        };
+        let entry = entry.user_data(user_data);
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a cancellation of another in-flight operation.
+    ///
+    /// By default this targets the op with `target_user_data`. Passing `fd`
+    /// and/or `op_code` matches on the op's file and/or opcode instead (add
+    /// `IORING_ASYNC_CANCEL_USERDATA` to also require the user_data to match).
+    /// `flags` takes the other `IORING_ASYNC_CANCEL_*` bits, e.g.
+    /// `IORING_ASYNC_CANCEL_ALL` to cancel every match rather than the first.
+    ///
+    /// The cancel's own `res` is 0 when it cancelled its target, `-ENOENT` if
+    /// nothing matched, or `-EALREADY` if the target was already completing.
+    /// With `IORING_ASYNC_CANCEL_ALL` it is instead the number of ops
+    /// cancelled, which may be 0.
+    #[pyo3(signature = (
+        user_data,
+        target_user_data,
+        flags = 0,
+        *,
+        fd = None,
+        op_code = None,
+        cookie = None,
+    ))]
+    fn prep_cancel(
+        &mut self,
+        user_data: u64,
+        target_user_data: u64,
+        flags: u32,
+        fd: Option<RawFd>,
+        op_code: Option<u8>,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        if let Some(fd) = fd {
+            check_fd("prep_cancel", fd)?;
+        }
+        let entry = opcode::AsyncCancel::new(target_user_data)
+            .build()
+            .user_data(user_data);
+        let entry = patch_sqe(entry, |sqe| {
+            sqe.op_flags = flags;
+            if let Some(fd) = fd {
+                sqe.fd = fd;
+                sqe.op_flags |= IORING_ASYNC_CANCEL_FD;
+            }
+            if let Some(op_code) = op_code {
+                sqe.len = op_code as u32;
+                sqe.op_flags |= IORING_ASYNC_CANCEL_OP;
+            }
+        });
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a socket creation.
+    ///
+    /// `flags` takes `SOCK_NONBLOCK`/`SOCK_CLOEXEC`, same as OR-ing them into
+    /// `sock_type`. The kernel reads creation flags from the type, and rejects
+    /// anything else.
+    #[pyo3(signature = (user_data, domain, sock_type, protocol = 0, flags = 0, *, cookie = None))]
+    fn prep_socket(
+        &mut self,
+        user_data: u64,
+        domain: i32,
+        sock_type: i32,
+        protocol: i32,
+        flags: u32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        let flags = check_socket_flags(flags)?;
+        let entry = opcode::Socket::new(domain, sock_type | flags, protocol)
+            .build()
+            .user_data(user_data);
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a recv from a connected socket into `buf`.
+    ///
+    /// With `poll_first=True` the kernel polls for readiness before trying the
+    /// recv, which saves a wasted attempt on mostly-idle connections.
+    ///
+    /// For datagram sockets, passing `MSG_TRUNC` in `flags` makes `res` the
+    /// datagram's real length even when it was cut short to fit `buf`, so
+    /// `res > len(buf)` detects truncation.
+    ///
+    /// Pass `buf=None, buf_group=id` instead of a buffer to have the kernel
+    /// pick one from a registered provided-buffer group when data arrives;
+    /// the event's `buffer_ids` then names it, see `read_buffer()`.
+    #[pyo3(signature = (
+        user_data,
+        fd,
+        buf = None,
+        flags = 0,
+        *,
+        poll_first = false,
+        buf_group = None,
+        cookie = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn prep_socket_recv(
+        &mut self,
+        _py: Python<'_>,
+        user_data: u64,
+        fd: RawFd,
+        buf: Option<Bound<'_, PyByteArray>>,
+        flags: u32,
+        poll_first: bool,
+        buf_group: Option<u16>,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_socket_recv", fd)?;
+        let entry = match (buf, buf_group) {
+            (Some(buf), None) => self.recv_entry(user_data, fd, buf, flags, poll_first)?,
+            (None, Some(group_id)) => {
+                self.recv_select_entry(user_data, fd, group_id, flags, poll_first)?
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "Pass exactly one of buf and buf_group",
+                ));
+            }
+        };
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a recv into `buf` that is cancelled if it hasn't completed within
+    /// `sec`/`nsec`.
+    ///
+    /// Pushes the recv with `IO_LINK` and a `LINK_TIMEOUT` right behind it, and
+    /// returns both user_data tokens as `(recv, timeout)`. On expiry the recv
+    /// completes with `-ECANCELED` and the timeout with `-ETIME`; otherwise
+    /// the timeout completes with `-ECANCELED`.
+    #[pyo3(signature = (user_data, fd, buf, sec, nsec, flags = 0, *, cookie = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn prep_socket_recv_timeout(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        buf: Bound<'_, PyByteArray>,
+        sec: u64,
+        nsec: u32,
+        flags: u32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<(u64, u64)> {
+        check_fd("prep_socket_recv_timeout", fd)?;
+        let entry = self.recv_entry(user_data, fd, buf, flags, false)?;
+        self.push_with_link_timeout(entry, sec, nsec)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a multishot recv drawing buffers from provided-buffer group `group_id`.
+    ///
+    /// Produces a CQE per chunk received while `IORING_CQE_F_MORE` is set. With
+    /// `bundle=True` (`IORING_RECVSEND_BUNDLE`) a single CQE may fill several
+    /// buffers; `res` is then the total byte count and `buffer_ids` lists the
+    /// buffers filled, in order.
+    #[pyo3(signature = (user_data, fd, group_id, flags = 0, *, bundle = false, cookie = None))]
+    fn prep_socket_recv_multishot(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        group_id: u16,
+        flags: u32,
+        bundle: bool,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_socket_recv_multishot", fd)?;
+        self.buf_ring(group_id)?;
+        let flags = check_msg_flags(flags)?;
+        let entry = if bundle {
+            opcode::RecvMultiBundle::new(types::Fd(fd), group_id)
+                .flags(flags)
+                .build()
+        } else {
+            opcode::RecvMulti::new(types::Fd(fd), group_id)
+                .flags(flags)
+                .build()
+        }
+        .user_data(user_data);
+
+        self.buffer_groups.insert(user_data, (group_id, bundle));
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a send of all of `bufs`, in order, as one bundle (6.10+).
+    ///
+    /// The buffers are exposed to the kernel through a one-off provided-buffer
+    /// ring, registered under the highest free buffer group id until the CQE
+    /// arrives. `res` is the total number of bytes sent, which may stop short
+    /// of the whole bundle.
+    #[pyo3(signature = (user_data, fd, bufs, flags = 0, *, cookie = None))]
+    fn prep_socket_send_bundle(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        bufs: Vec<Bound<'_, PyBytes>>,
+        flags: u32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_socket_send_bundle", fd)?;
+        let flags = check_msg_flags(flags)?;
+        let group_id = (0..=u16::MAX)
+            .rev()
+            .find(|id| {
+                !self.buf_rings.contains_key(id)
+                    && !self.send_bundles.values().any(|b| b.group_id == *id)
+            })
+            .ok_or_else(|| RingError::new_err("No free buffer group id"))?;
+        let bundle = SendBundleRing::new(group_id, bufs)?;
+
+        // SAFETY: the ring memory lives in `self.send_bundles` until the op
+        // completes, or until after the io_uring instance is dropped.
+        unsafe {
+            self.uring()?
+                .submitter()
+                .register_buf_ring_with_flags(
+                    bundle.ring.as_ptr() as u64,
+                    bundle.entries(),
+                    group_id,
+                    0,
+                )
+                .map_err(|e| {
+                    RingError::new_err(format!("io_uring_register_buf_ring failed: {e}"))
+                })?;
+        }
+        let entry = opcode::SendBundle::new(types::Fd(fd), group_id)
+            .flags(flags)
+            .build()
+            .user_data(user_data);
+
+        self.send_bundles.insert(user_data, bundle);
+        self.push_entry(entry)
+            .inspect_err(|_| self.release_pinned(user_data))
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
 
+    /// Prep a recvmsg scattering into `bufs`, in order.
+    ///
+    /// The source address, ancillary (control) data and message flags land in
+    /// `header`, readable once the CQE has arrived. `res` is the total number
+    /// of bytes received across `bufs`. A datagram that didn't fit is cut
+    /// short and flagged with `MSG_TRUNC` in `header.msg_flags`; passing
+    /// `MSG_TRUNC` in `flags` too makes `res` its real length, as for
+    /// `prep_socket_recv`. A `header` serves one op at a time: reusing it
+    /// before the earlier op's CQE is reaped raises `ValueError`.
+    #[pyo3(signature = (user_data, fd, bufs, header, flags = 0, *, cookie = None))]
+    fn prep_recvmsg(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        bufs: Vec<Bound<'_, PyByteArray>>,
+        header: Bound<'_, RecvMsgHeader>,
+        flags: u32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_recvmsg", fd)?;
+        let flags = check_msg_flags(flags)? as u32;
+        let mut guard = header.borrow_mut();
+        let msghdr_ptr = guard.prepare(&bufs)?;
+        let in_flight = guard.in_flight.clone();
         drop(guard);
 
-        self.pinned_statx_buffers.insert(
+        let entry = opcode::RecvMsg::new(types::Fd(fd), msghdr_ptr)
+            .flags(flags)
+            .build()
+            .user_data(user_data);
+
+        self.pin(
             user_data,
-            StatxRequest {
-                path: c_path,
-                statxbuf: buf.unbind(),
-            },
+            Pinned::RecvMsg(RecvMsgRequest {
+                bufs: bufs.into_iter().map(Bound::unbind).collect(),
+                header: header.unbind(),
+                in_flight,
+            }),
         );
         self.push_entry(entry)
+            .inspect_err(|_| self.release_pinned(user_data))
+            .inspect(|_| self.set_cookie(user_data, cookie))
     }
 
-    /// Prep a file write.
-    #[pyo3(signature = (user_data, fd, buf, offset))]
-    fn prep_write(
+    /// Prep a zero-copy `sendmsg` (6.1+) of `bufs`, gathered into one
+    /// datagram (or stream write), to `addr`, or `None` for a connected
+    /// socket.
+    ///
+    /// It posts two CQEs for `user_data`: the send's, with `res` the bytes
+    /// sent and `more` set, then a notification (`is_notification`, `res`
+    /// 0) once the kernel is done with the buffers, which stay pinned until
+    /// then. A send that fails posts only its error.
+    #[pyo3(signature = (user_data, fd, bufs, addr, flags = 0, *, cookie = None))]
+    fn prep_sendmsg_zc(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        bufs: Vec<Bound<'_, PyBytes>>,
+        addr: Option<SockAddr>,
+        flags: u32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_sendmsg_zc", fd)?;
+        let flags = check_msg_flags(flags)? as u32;
+        let mut iovecs: Vec<libc::iovec> = bufs
+            .iter()
+            .map(|buf| libc::iovec {
+                iov_base: buf.as_bytes().as_ptr() as *mut libc::c_void,
+                iov_len: buf.as_bytes().len(),
+            })
+            .collect();
+        // SAFETY: an all-zero msghdr is a valid empty one.
+        let mut msghdr: Box<libc::msghdr> = Box::new(unsafe { std::mem::zeroed() });
+        if let Some(addr) = &addr {
+            let (ptr, len) = addr.inner.as_ptr_and_len();
+            msghdr.msg_name = ptr as *mut libc::c_void;
+            msghdr.msg_namelen = len;
+        }
+        msghdr.msg_iov = iovecs.as_mut_ptr();
+        msghdr.msg_iovlen = iovecs.len();
+
+        let entry = opcode::SendMsgZc::new(types::Fd(fd), &*msghdr)
+            .flags(flags)
+            .build()
+            .user_data(user_data);
+
+        self.pin(
+            user_data,
+            Pinned::SendMsg(SendMsgRequest {
+                msghdr,
+                iovecs,
+                bufs: bufs.into_iter().map(Bound::unbind).collect(),
+                addr: addr.map(|addr| addr.inner),
+            }),
+        );
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a send to a connected socket.
+    ///
+    /// `poll_first=True` behaves as for `prep_socket_recv`.
+    #[pyo3(signature = (user_data, fd, buf, flags = 0, *, poll_first = false, cookie = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn prep_socket_send(
         &mut self,
         _py: Python<'_>,
         user_data: u64,
         fd: RawFd,
         buf: Bound<'_, PyBytes>,
-        offset: u64,
+        flags: u32,
+        poll_first: bool,
+        cookie: Option<Py<PyAny>>,
     ) -> PyResult<()> {
+        check_fd("prep_socket_send", fd)?;
+        let flags = check_msg_flags(flags)?;
         let data = buf.as_bytes();
         let ptr = data.as_ptr();
         let len = data.len() as u32;
 
-        let entry = opcode::Write::new(types::Fd(fd), ptr.cast(), len)
-            .offset(offset)
+        let mut entry = opcode::Send::new(types::Fd(fd), ptr.cast(), len)
+            .flags(flags)
             .build()
             .user_data(user_data);
+        if poll_first {
+            entry = patch_sqe(entry, |sqe| sqe.ioprio |= IORING_RECVSEND_POLL_FIRST);
+        }
 
-        self.pinned_immutable_buffers
-            .insert(user_data, buf.unbind());
+        self.pin(user_data, Pinned::ImmutableBuffer(buf.unbind()));
         self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
     }
 
-    /// Prep a file open.
-    #[pyo3(signature = (user_data, path, flags, mode, dir_fd))]
-    fn prep_openat(
+    /// Prep a send of all of `buf`, re-sending the remainder after short sends.
+    ///
+    /// Only the final completion is surfaced, with `res` the total number of
+    /// bytes sent, or the error that stopped the send. Follow-up sends go out
+    /// with the next submit or wait. If the SQ has no room for one, the op
+    /// completes early with the short count.
+    #[pyo3(signature = (user_data, fd, buf, flags = 0, *, cookie = None))]
+    fn prep_socket_send_all(
         &mut self,
         user_data: u64,
-        path: &str,
-        flags: i32,
-        mode: u32,
-        dir_fd: RawFd,
+        fd: RawFd,
+        buf: Bound<'_, PyBytes>,
+        flags: u32,
+        cookie: Option<Py<PyAny>>,
     ) -> PyResult<()> {
-        let c_path =
-            CString::new(path).map_err(|_| PyRuntimeError::new_err("Path contains null byte"))?;
-        let ptr = c_path.as_ptr();
+        check_fd("prep_socket_send_all", fd)?;
+        let data = buf.as_bytes();
+        let state = SendAllState {
+            addr: data.as_ptr() as usize,
+            len: data.len(),
+            sent: 0,
+            fd,
+            flags: check_msg_flags(flags)?,
+        };
+        let entry = Self::send_all_entry(user_data, &state);
+
+        self.pin(user_data, Pinned::ImmutableBuffer(buf.unbind()));
+        self.send_alls.insert(user_data, state);
+        self.push_entry(entry)
+            .inspect_err(|_| self.release_pinned(user_data))
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Preps to bind to a socket.
+    #[pyo3(signature = (user_data, fd, sock_addr, *, cookie = None))]
+    fn prep_socket_bind(
+        &mut self,
+        _py: Python<'_>,
+        user_data: u64,
+        fd: RawFd,
+        sock_addr: SockAddr,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_socket_bind", fd)?;
+        let (ptr, len) = sock_addr.inner.as_ptr_and_len();
+        self.pin(user_data, Pinned::SockAddr(sock_addr.inner));
+
+        let entry = opcode::Bind::new(types::Fd(fd), ptr, len)
+            .build()
+            .user_data(user_data);
+
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prepares a socket to listen. Marks it as passive.
+    #[pyo3(signature = (user_data, fd, backlog, *, cookie = None))]
+    fn prep_socket_listen(
+        &mut self,
+        _py: Python<'_>,
+        user_data: u64,
+        fd: RawFd,
+        backlog: i32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_socket_listen", fd)?;
+        let entry = opcode::Listen::new(types::Fd(fd), backlog)
+            .build()
+            .user_data(user_data);
+
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
 
-        let entry = opcode::OpenAt::new(types::Fd(dir_fd), ptr)
+    /// Prepares a socket to accept an incoming connection.
+    ///
+    /// `flags` takes `SOCK_NONBLOCK`/`SOCK_CLOEXEC`, applied to the accepted
+    /// socket atomically (like `accept4(2)`).
+    /// `res` is a regular fd for the new connection (never a direct
+    /// descriptor); `socket_from_fd(res)` wraps it as a `socket.socket`.
+    /// TODO: Add sockaddr for kernel to fill, for logging who connected.
+    #[pyo3(signature = (user_data, fd, flags = 0, *, cookie = None))]
+    fn prep_socket_accept(
+        &mut self,
+        _py: Python<'_>,
+        user_data: u64,
+        fd: RawFd,
+        flags: u32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_socket_accept", fd)?;
+        let flags = check_socket_flags(flags)?;
+        let entry = opcode::Accept::new(types::Fd(fd), std::ptr::null_mut(), std::ptr::null_mut())
             .flags(flags)
-            .mode(mode)
             .build()
             .user_data(user_data);
 
-        // Pin the CString so the pointer stays valid until CQE
-        self.pinned_paths.insert(user_data, c_path);
         self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Connects to a socket from a client.
+    #[pyo3(signature = (user_data, fd, sock_addr, *, cookie = None))]
+    fn prep_socket_connect(
+        &mut self,
+        _py: Python<'_>,
+        user_data: u64,
+        fd: RawFd,
+        sock_addr: SockAddr,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_socket_connect", fd)?;
+        let entry = self.connect_entry(user_data, fd, sock_addr);
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a connect that is cancelled if it hasn't completed within
+    /// `sec`/`nsec`, e.g. because the host is unreachable.
+    ///
+    /// Like `prep_socket_recv_timeout`, returns `(connect, timeout)` user_data
+    /// tokens. On expiry the connect completes with `-ECANCELED` and the
+    /// timeout with `-ETIME`.
+    #[pyo3(signature = (user_data, fd, sock_addr, sec, nsec, *, cookie = None))]
+    fn prep_socket_connect_timeout(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        sock_addr: SockAddr,
+        sec: u64,
+        nsec: u32,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<(u64, u64)> {
+        check_fd("prep_socket_connect_timeout", fd)?;
+        let entry = self.connect_entry(user_data, fd, sock_addr);
+        self.push_with_link_timeout(entry, sec, nsec)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+
+    /// Prep a connect hard-linked to a `getsockopt(SO_ERROR)` into `error`.
+    ///
+    /// Returns `(connect_user_data, getsockopt_user_data)`; the latter is
+    /// `user_data` with `SO_ERROR_TAG` (bit 62) set. The link is a hard link,
+    /// so the getsockopt runs even when the connect fails. Once both CQEs are
+    /// in, `error.error` holds the connect error (0 if connected). Needs a
+    /// 6.7+ kernel.
+    #[pyo3(signature = (user_data, fd, sock_addr, error, *, cookie = None))]
+    fn prep_socket_connect_checked(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        sock_addr: SockAddr,
+        error: Bound<'_, SockErrorBuffer>,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<(u64, u64)> {
+        check_fd("prep_socket_connect_checked", fd)?;
+        check_user_data(user_data)?;
+        let getsockopt_user_data = user_data | SO_ERROR_TAG;
+
+        let connect = self
+            .connect_entry(user_data, fd, sock_addr)
+            .flags(squeue::Flags::IO_HARDLINK);
+
+        let optval = error.get().so_error.as_ptr();
+        let getsockopt = opcode::UringCmd16::new(types::Fd(fd), SOCKET_URING_OP_GETSOCKOPT)
+            .build()
+            .user_data(getsockopt_user_data);
+        let getsockopt = patch_sqe(getsockopt, |sqe| {
+            sqe.addr = libc::SOL_SOCKET as u64 | (libc::SO_ERROR as u64) << 32;
+            sqe.splice_fd_in = std::mem::size_of::<i32>() as i32;
+            sqe.addr3 = optval as u64;
+        });
+        self.pin(getsockopt_user_data, Pinned::SocketError(error.unbind()));
+
+        self.push_own_entries(&[connect, getsockopt])?;
+        self.set_cookie(user_data, cookie);
+        Ok((user_data, getsockopt_user_data))
+    }
+
+    /// Prep a TCP listener on `sock_addr` as one linked chain: socket,
+    /// `SO_REUSEADDR` (if `reuseaddr`), bind and listen, then install it as a
+    /// regular close-on-exec fd.
+    ///
+    /// The ops take consecutive user_data tokens from `user_data` on, which
+    /// are returned in chain order. The last op's `res` is the listening fd;
+    /// if an earlier op fails, it reports the error and the ops after it
+    /// complete with `-ECANCELED`. The socket is built as a direct
+    /// descriptor, so needs 6.11+ (bind/listen) and at most 16 chains may be
+    /// in flight at once.
+    ///
+    /// The direct descriptors live in a 16-slot file table registered on the
+    /// first call. A ring has only one file table, so this raises
+    /// `ValueError` after `register_files_sparse`, and makes
+    /// `register_files_sparse` raise from then on.
+    #[pyo3(signature = (user_data, sock_addr, backlog = 128, *, reuseaddr = true, cookie = None))]
+    fn prep_listener(
+        &mut self,
+        user_data: u64,
+        sock_addr: SockAddr,
+        backlog: i32,
+        reuseaddr: bool,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<Vec<u64>> {
+        let slot = self.take_listener_slot()?;
+        let fd = types::Fixed(slot);
+        let domain = match *sock_addr.inner {
+            SockAddrInner::V4(_) => libc::AF_INET,
+            SockAddrInner::V6(_) => libc::AF_INET6,
+        };
+        let dest = types::DestinationSlot::try_from_slot_target(slot)
+            .map_err(|_| RingError::new_err("Invalid listener slot"))?;
+
+        let mut entries = vec![
+            opcode::Socket::new(domain, libc::SOCK_STREAM, 0)
+                .file_index(Some(dest))
+                .build(),
+        ];
+        let optval = Box::new(1i32);
+        if reuseaddr {
+            entries.push(
+                opcode::SetSockOpt::new(
+                    fd,
+                    libc::SOL_SOCKET as u32,
+                    libc::SO_REUSEADDR as u32,
+                    optval.as_ref() as *const i32 as *const libc::c_void,
+                    std::mem::size_of::<i32>() as u32,
+                )
+                .build(),
+            );
+        }
+        let (addr, addr_len) = sock_addr.inner.as_ptr_and_len();
+        entries.push(opcode::Bind::new(fd, addr, addr_len).build());
+        entries.push(opcode::Listen::new(fd, backlog).build());
+        entries.push(opcode::FixedFdInstall::new(fd, 0).build());
+
+        let last = entries.len() - 1;
+        let tokens: Vec<u64> = (0..entries.len() as u64)
+            .map(|i| user_data.wrapping_add(i))
+            .collect();
+        let entries: Vec<squeue::Entry> = entries
+            .into_iter()
+            .zip(&tokens)
+            .enumerate()
+            .map(|(i, (entry, &token))| {
+                let entry = entry.user_data(token);
+                if i < last {
+                    entry.flags(squeue::Flags::IO_LINK)
+                } else {
+                    entry
+                }
+            })
+            .collect();
+
+        if reuseaddr {
+            self.pin(tokens[1], Pinned::Sockopt(optval));
+        }
+        self.pin(tokens[last - 2], Pinned::SockAddr(sock_addr.inner));
+        self.listener_slots.insert(tokens[last], slot);
+        self.push_entries(&entries)
+            .inspect_err(|_| self.release_pinned_batch(&tokens))?;
+        self.set_cookie(user_data, cookie);
+        Ok(tokens)
+    }
+
+    /// Set socket options.
+    #[pyo3(signature = (user_data, fd, *, cookie = None))]
+    fn prep_socket_setopt(
+        &mut self,
+        user_data: u64,
+        fd: RawFd,
+        cookie: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        check_fd("prep_socket_setopt", fd)?;
+        // TODO: Hardcoded for now.
+        let optval = Box::new(1i32); // SO_REUSEADDR value
+
+        let entry = opcode::SetSockOpt::new(
+            types::Fd(fd),
+            libc::SOL_SOCKET as u32,
+            libc::SO_REUSEADDR as u32,
+            optval.as_ref() as *const i32 as *const libc::c_void,
+            std::mem::size_of::<i32>() as u32,
+        )
+        .build()
+        .user_data(user_data);
+
+        self.pin(user_data, Pinned::Sockopt(optval));
+        self.push_entry(entry)
+            .inspect(|_| self.set_cookie(user_data, cookie))
+    }
+}
+
+/// The `io_uring_params` of a ring, as returned by `Ring.params()`.
+///
+/// `flags` are the `IORING_SETUP_*` flags the ring was created with and
+/// `features` the `IORING_FEAT_*` bits the kernel supports. `sq_off` and
+/// `cq_off` map the fields of `io_sqring_offsets`/`io_cqring_offsets` to
+/// their offsets in the mmapped rings.
+#[pyclass(frozen)]
+struct RingParams {
+    #[pyo3(get)]
+    sq_entries: u32,
+    #[pyo3(get)]
+    cq_entries: u32,
+    #[pyo3(get)]
+    flags: u32,
+    #[pyo3(get)]
+    features: u32,
+    #[pyo3(get)]
+    sq_thread_cpu: u32,
+    #[pyo3(get)]
+    sq_thread_idle: u32,
+    #[pyo3(get)]
+    sq_off: HashMap<&'static str, u32>,
+    #[pyo3(get)]
+    cq_off: HashMap<&'static str, u32>,
+}
+
+#[pymethods]
+impl RingParams {
+    fn __repr__(&self) -> String {
+        format!(
+            "RingParams(sq_entries={}, cq_entries={}, flags={:#x}, features={:#x})",
+            self.sq_entries, self.cq_entries, self.flags, self.features
+        )
+    }
+}
+
+/// Context manager returned by `Ring.chain()`.
+#[pyclass(frozen)]
+struct Chain {
+    ring: Py<Ring>,
+}
+
+#[pymethods]
+impl Chain {
+    fn __enter__(&self, py: Python<'_>) -> PyResult<Py<Ring>> {
+        let mut ring = self.ring.borrow_mut(py);
+        ring.uring()?;
+        if ring.chain.is_some() {
+            return Err(RingError::new_err("A chain is already open on this ring"));
+        }
+        ring.chain = Some(Vec::new());
+        Ok(self.ring.clone_ref(py))
+    }
+
+    #[pyo3(signature = (exc_type=None, _exc_val=None, _exc_tb=None))]
+    fn __exit__(
+        &self,
+        py: Python<'_>,
+        exc_type: Option<&Bound<'_, PyAny>>,
+        _exc_val: Option<&Bound<'_, PyAny>>,
+        _exc_tb: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<bool> {
+        let mut ring = self.ring.borrow_mut(py);
+        let Some(mut entries) = ring.chain.take() else {
+            // The ring was closed inside the block.
+            return Ok(false);
+        };
+        let raised = exc_type.is_some_and(|t| !t.is_none());
+        let pushed = match entries.split_last_mut() {
+            Some((last, init)) if !raised => {
+                for entry in init {
+                    *entry = entry.clone().flags(squeue::Flags::IO_LINK);
+                }
+                *last = patch_sqe(last.clone(), |raw| {
+                    raw.flags &= !squeue::Flags::IO_LINK.bits()
+                });
+                ring.push_entries(&entries)
+            }
+            _ => Ok(()),
+        };
+        if raised || pushed.is_err() {
+            let user_datas: Vec<u64> = entries.iter().map(|e| e.get_user_data()).collect();
+            ring.release_pinned_batch(&user_datas);
+        }
+        pushed.map(|()| false)
+    }
+}
+
+/// Page-aligned, zeroed memory for a provided-buffer ring of `entries` entries.
+fn alloc_ring_entries(entries: u16) -> PyResult<(NonNull<types::BufRingEntry>, Layout)> {
+    let layout = Layout::from_size_align(
+        entries as usize * std::mem::size_of::<types::BufRingEntry>(),
+        4096,
+    )
+    .map_err(|e| PyValueError::new_err(format!("Invalid buffer ring layout: {e}")))?;
+    // SAFETY: layout has a non-zero size.
+    let ptr = unsafe { alloc_zeroed(layout) } as *mut types::BufRingEntry;
+    let ring =
+        NonNull::new(ptr).ok_or_else(|| RingError::new_err("Failed to allocate buffer ring"))?;
+    Ok((ring, layout))
+}
+
+/// Make ring entries up to `tail` visible to the kernel.
+fn publish_ring_tail(ring: NonNull<types::BufRingEntry>, tail: u16) {
+    // SAFETY: the tail overlays the first entry's `resv` field, which the
+    // kernel reads atomically.
+    let shared = unsafe { &*(types::BufRingEntry::tail(ring.as_ptr()) as *const AtomicU16) };
+    shared.store(tail, Ordering::Release);
+}
+
+/// A one-off provided-buffer ring whose entries point at the `bytes` of a
+/// bundled send. Registered for the duration of that single op.
+struct SendBundleRing {
+    ring: NonNull<types::BufRingEntry>,
+    layout: Layout,
+    group_id: u16,
+    /// Keeps the data the ring entries point at alive.
+    _bufs: Vec<Py<PyBytes>>,
+}
+
+// SAFETY: see `BufRing`.
+unsafe impl Send for SendBundleRing {}
+unsafe impl Sync for SendBundleRing {}
+
+impl SendBundleRing {
+    fn new(group_id: u16, bufs: Vec<Bound<'_, PyBytes>>) -> PyResult<Self> {
+        if bufs.is_empty() || bufs.len() > 1 << 15 {
+            return Err(PyValueError::new_err(
+                "A send bundle takes between 1 and 32768 buffers",
+            ));
+        }
+        let entries = (bufs.len() as u16).next_power_of_two();
+        let (ring, layout) = alloc_ring_entries(entries)?;
+        for (bid, buf) in bufs.iter().enumerate() {
+            let data = buf.as_bytes();
+            // SAFETY: bid < bufs.len() <= entries.
+            let entry = unsafe { &mut *ring.as_ptr().add(bid) };
+            entry.set_addr(data.as_ptr() as u64);
+            entry.set_len(data.len() as u32);
+            entry.set_bid(bid as u16);
+        }
+        publish_ring_tail(ring, bufs.len() as u16);
+        Ok(SendBundleRing {
+            ring,
+            layout,
+            group_id,
+            _bufs: bufs.into_iter().map(Bound::unbind).collect(),
+        })
+    }
+
+    fn entries(&self) -> u16 {
+        (self.layout.size() / std::mem::size_of::<types::BufRingEntry>()) as u16
+    }
+}
+
+impl Drop for SendBundleRing {
+    fn drop(&mut self) {
+        // SAFETY: allocated in `new` with the same layout.
+        unsafe { dealloc(self.ring.as_ptr().cast(), self.layout) };
+    }
+}
+
+/// Zeroed heap memory the kernel reads and writes behind our back.
+///
+/// Pointers into it come from the allocation itself, never from a Rust
+/// reference, so handing one to the kernel while `&self` is shared doesn't
+/// break aliasing rules.
+struct KernelBuffers {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+// SAFETY: the memory is exclusively owned; concurrent access is by the kernel
+// and coordinated by the owner's bookkeeping.
+unsafe impl Send for KernelBuffers {}
+unsafe impl Sync for KernelBuffers {}
+
+impl KernelBuffers {
+    fn new(len: usize) -> PyResult<Self> {
+        let layout = Layout::from_size_align(len, 1)
+            .ok()
+            .filter(|layout| layout.size() > 0)
+            .ok_or_else(|| PyValueError::new_err(format!("Invalid buffer size: {len}")))?;
+        // SAFETY: layout has a non-zero size.
+        let ptr = NonNull::new(unsafe { alloc_zeroed(layout) })
+            .ok_or_else(|| RingError::new_err("Failed to allocate buffers"))?;
+        Ok(KernelBuffers { ptr, layout })
+    }
+
+    /// Pointer `offset` bytes in. The caller keeps `offset` in bounds.
+    fn at(&self, offset: usize) -> *mut u8 {
+        debug_assert!(offset <= self.layout.size());
+        // SAFETY: in bounds of the allocation, per the caller.
+        unsafe { self.ptr.as_ptr().add(offset) }
+    }
+
+    /// Copy the `out.len()` bytes at `offset` into `out`. The caller keeps
+    /// the range in bounds and out of reach of in-flight ops.
+    fn read_into(&self, offset: usize, out: &mut [u8]) {
+        debug_assert!(offset + out.len() <= self.layout.size());
+        // SAFETY: in bounds, and `out` can't overlap memory we own.
+        unsafe { ptr::copy_nonoverlapping(self.at(offset), out.as_mut_ptr(), out.len()) };
+    }
+
+    /// New `bytes` holding the `len` bytes at `offset`, as for `read_into`.
+    fn to_bytes<'py>(
+        &self,
+        py: Python<'py>,
+        offset: usize,
+        len: usize,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        PyBytes::new_with(py, len, |out| {
+            self.read_into(offset, out);
+            Ok(())
+        })
     }
+}
 
-    /// Prep a file/socket close.
-    fn prep_close(&mut self, user_data: u64, fd: RawFd) -> PyResult<()> {
-        let entry = opcode::Close::new(types::Fd(fd))
-            .build()
-            .user_data(user_data);
-        self.push_entry(entry)
+impl Drop for KernelBuffers {
+    fn drop(&mut self) {
+        // SAFETY: allocated in `new` with the same layout.
+        unsafe { dealloc(self.ptr.as_ptr(), self.layout) };
     }
+}
 
-    /// Prep a cancellation of another in-flight operation.
-    #[pyo3(signature = (user_data, target_user_data, flags = 0))]
-    fn prep_cancel(&mut self, user_data: u64, target_user_data: u64, flags: i32) -> PyResult<()> {
-        let entry = opcode::AsyncCancel::new(target_user_data)
-            .build()
-            .user_data(user_data);
-        // TODO: use flags once io-uring crate exposes cancel flags
-        let _ = flags;
-        self.push_entry(entry)
+/// A provided-buffer ring (`IORING_REGISTER_PBUF_RING`).
+///
+/// Owns both the page-aligned ring of entries shared with the kernel and the
+/// backing buffers, `buf_size` bytes per buffer id.
+struct BufRing {
+    ring: NonNull<types::BufRingEntry>,
+    layout: Layout,
+    buffers: KernelBuffers,
+    buf_size: u32,
+    entries: u16,
+    /// Local tail; made visible to the kernel by `publish`.
+    tail: u16,
+    /// Ring position each buffer id was last pushed to.
+    positions: Vec<u16>,
+    /// Buffer ids picked by a completion and not recycled yet.
+    consumed: Vec<bool>,
+}
+
+// SAFETY: the ring memory is exclusively owned, and only touched through `&mut Ring`.
+unsafe impl Send for BufRing {}
+unsafe impl Sync for BufRing {}
+
+impl BufRing {
+    fn new(entries: u16, buf_size: u32) -> PyResult<Self> {
+        if !entries.is_power_of_two() || entries > 1 << 15 {
+            return Err(PyValueError::new_err(
+                "Buffer ring entries must be a power of two, at most 32768",
+            ));
+        }
+        let buffers = KernelBuffers::new(entries as usize * buf_size as usize)?;
+        let (ring, layout) = alloc_ring_entries(entries)?;
+        let mut buf_ring = BufRing {
+            ring,
+            layout,
+            buffers,
+            buf_size,
+            entries,
+            tail: 0,
+            positions: vec![0; entries as usize],
+            consumed: vec![false; entries as usize],
+        };
+        for bid in 0..entries {
+            buf_ring.push(bid);
+        }
+        buf_ring.publish();
+        Ok(buf_ring)
     }
 
-    /// Prep a socket creation.
-    #[pyo3(signature = (user_data, domain, sock_type, protocol = 0, flags = 0))]
-    fn prep_socket(
-        &mut self,
-        user_data: u64,
-        domain: i32,
-        sock_type: i32,
-        protocol: i32,
-        flags: u32,
-    ) -> PyResult<()> {
-        let entry = opcode::Socket::new(domain, sock_type, protocol)
-            .build()
-            .user_data(user_data);
-        let _ = flags; // TODO: pass flags if opcode supports it
-        self.push_entry(entry)
+    fn ring_addr(&self) -> u64 {
+        self.ring.as_ptr() as u64
     }
 
-    /// Prep a recv from a connected socket into `buf`.
-    #[pyo3(signature = (user_data, fd, buf, flags = 0))]
-    fn prep_socket_recv(
-        &mut self,
-        _py: Python<'_>,
-        user_data: u64,
-        fd: RawFd,
-        buf: Bound<'_, PyByteArray>,
-        flags: u32,
-    ) -> PyResult<()> {
-        let ptr = buf.data();
-        let len = buf.len() as u32;
+    fn entry(&self, position: u16) -> &types::BufRingEntry {
+        let index = (position & (self.entries - 1)) as usize;
+        // SAFETY: index is masked into the ring's bounds.
+        unsafe { &*self.ring.as_ptr().add(index) }
+    }
 
-        let entry = opcode::Recv::new(types::Fd(fd), ptr.cast(), len)
-            .flags(flags as i32)
-            .build()
-            .user_data(user_data);
+    /// Queue `bid` for reuse by the kernel. Takes effect on `publish`.
+    fn push(&mut self, bid: u16) {
+        let index = (self.tail & (self.entries - 1)) as usize;
+        let addr = self.buffers.at(bid as usize * self.buf_size as usize) as u64;
+        // SAFETY: index is masked into the ring's bounds.
+        let entry = unsafe { &mut *self.ring.as_ptr().add(index) };
+        entry.set_addr(addr);
+        entry.set_len(self.buf_size);
+        entry.set_bid(bid);
+        self.positions[bid as usize] = self.tail;
+        self.tail = self.tail.wrapping_add(1);
+    }
 
-        self.pinned_mutable_buffers.insert(user_data, buf.unbind());
-        self.push_entry(entry)
+    /// Make pushed entries visible to the kernel.
+    fn publish(&self) {
+        publish_ring_tail(self.ring, self.tail);
     }
 
-    /// Prep a send to a connected socket.
-    #[pyo3(signature = (user_data, fd, buf, flags = 0))]
-    fn prep_socket_send(
-        &mut self,
-        _py: Python<'_>,
-        user_data: u64,
-        fd: RawFd,
-        buf: Bound<'_, PyBytes>,
-        flags: u32,
-    ) -> PyResult<()> {
-        let data = buf.as_bytes();
-        let ptr = data.as_ptr();
-        let len = data.len() as u32;
+    /// Buffer ids filled by a completion of `res` bytes starting at buffer
+    /// `first`, which are marked consumed.
+    ///
+    /// Only a `bundle` spans several buffers: it consumes consecutive ring
+    /// entries, so the ids after the first are read back from the ring. Any
+    /// other op uses one buffer, even if `res` is larger (e.g. a recv with
+    /// `MSG_TRUNC` reporting a datagram's real length).
+    fn consumed(&mut self, first: u16, res: i32, bundle: bool) -> Vec<u16> {
+        let count = if bundle {
+            (res.max(1) as u32).div_ceil(self.buf_size).max(1)
+        } else {
+            1
+        };
+        let start = self.positions[first as usize];
+        let bids: Vec<u16> = (0..count)
+            .map(|i| self.entry(start.wrapping_add(i as u16)).bid())
+            .collect();
+        for &bid in &bids {
+            self.consumed[bid as usize] = true;
+        }
+        bids
+    }
 
-        let entry = opcode::Send::new(types::Fd(fd), ptr.cast(), len)
-            .flags(flags as i32)
-            .build()
-            .user_data(user_data);
+    /// Hand consumed buffer `bid` back to the kernel.
+    fn recycle(&mut self, bid: u16) -> PyResult<()> {
+        if !self
+            .consumed
+            .get(bid as usize)
+            .is_some_and(|&consumed| consumed)
+        {
+            return Err(PyValueError::new_err(format!(
+                "Buffer {bid} is not consumed (ring of {} buffers)",
+                self.entries
+            )));
+        }
+        self.consumed[bid as usize] = false;
+        self.push(bid);
+        self.publish();
+        Ok(())
+    }
 
-        self.pinned_immutable_buffers
-            .insert(user_data, buf.unbind());
-        self.push_entry(entry)
+    /// Copy of the first `nbytes` of buffer `bid`.
+    fn buffer<'py>(&self, py: Python<'py>, bid: u16, nbytes: u32) -> PyResult<Bound<'py, PyBytes>> {
+        if bid >= self.entries || nbytes > self.buf_size {
+            return Err(PyValueError::new_err(format!(
+                "Buffer {bid} has no {nbytes}-byte range (ring of {} x {} bytes)",
+                self.entries, self.buf_size
+            )));
+        }
+        let start = bid as usize * self.buf_size as usize;
+        self.buffers.to_bytes(py, start, nbytes as usize)
     }
+}
 
-    /// Preps to bind to a socket.
-    fn prep_socket_bind(
-        &mut self,
-        _py: Python<'_>,
-        user_data: u64,
-        fd: RawFd,
-        sock_addr: SockAddr,
-    ) -> PyResult<()> {
-        self.pinned_sockaddr.insert(user_data, sock_addr.inner);
+impl Drop for BufRing {
+    fn drop(&mut self) {
+        // SAFETY: allocated in `new` with the same layout.
+        unsafe { dealloc(self.ring.as_ptr().cast(), self.layout) };
+    }
+}
 
-        let stored = self.pinned_sockaddr.get(&user_data).unwrap();
-        let (ptr, len) = stored.as_ptr_and_len();
+/// Registered buffers handed out round-robin, one per in-flight op.
+///
+/// A buffer goes back to the end of the free list when its op's CQE is
+/// reaped, so its contents stay intact until `count - 1` more buffers have
+/// been acquired.
+#[pyclass(frozen)]
+struct BufferPool {
+    buffers: KernelBuffers,
+    buf_size: u32,
+    count: u16,
+    state: Mutex<BufferPoolState>,
+}
 
-        let entry = opcode::Bind::new(types::Fd(fd), ptr, len)
-            .build()
-            .user_data(user_data);
+struct BufferPoolState {
+    free: VecDeque<u16>,
+    /// Buffer index held by each in-flight op, keyed by `user_data`.
+    in_flight: HashMap<u64, u16>,
+}
 
-        self.push_entry(entry)
+impl BufferPool {
+    fn new(count: u16, buf_size: u32) -> PyResult<Self> {
+        if count == 0 || buf_size == 0 {
+            return Err(PyValueError::new_err(
+                "Buffer pool count and buf_size must be non-zero",
+            ));
+        }
+        Ok(BufferPool {
+            buffers: KernelBuffers::new(count as usize * buf_size as usize)?,
+            buf_size,
+            count,
+            state: Mutex::new(BufferPoolState {
+                free: (0..count).collect(),
+                in_flight: HashMap::new(),
+            }),
+        })
     }
 
-    /// Prepares a socket to listen. Marks it as passive.
-    fn prep_socket_listen(
-        &mut self,
-        _py: Python<'_>,
-        user_data: u64,
-        fd: RawFd,
-        backlog: i32,
-    ) -> PyResult<()> {
-        let entry = opcode::Listen::new(types::Fd(fd), backlog)
-            .build()
-            .user_data(user_data);
+    fn iovecs(&self) -> Vec<libc::iovec> {
+        (0..self.count as usize)
+            .map(|index| libc::iovec {
+                iov_base: self.buffers.at(index * self.buf_size as usize).cast(),
+                iov_len: self.buf_size as usize,
+            })
+            .collect()
+    }
 
-        self.push_entry(entry)
+    /// Start in `buffers` of the `nbytes` at `offset` into buffer `index`.
+    fn check_range(&self, index: u16, offset: u32, nbytes: u32) -> PyResult<usize> {
+        if index >= self.count || offset as u64 + nbytes as u64 > self.buf_size as u64 {
+            return Err(PyValueError::new_err(format!(
+                "Buffer {index} has no {nbytes}-byte range at offset {offset} \
+                 (pool of {} x {} bytes)",
+                self.count, self.buf_size
+            )));
+        }
+        Ok(index as usize * self.buf_size as usize + offset as usize)
     }
 
-    /// Prepares a socket to accept an incoming connection.
-    /// TODO: Add sockaddr for kernel to fill, for logging who connected.
-    fn prep_socket_accept(&mut self, _py: Python<'_>, user_data: u64, fd: RawFd) -> PyResult<()> {
-        let entry = opcode::Accept::new(types::Fd(fd), std::ptr::null_mut(), std::ptr::null_mut())
-            .build()
-            .user_data(user_data);
+    fn buffer_ptr(&self, index: u16, offset: u32, nbytes: u32) -> PyResult<*mut u8> {
+        let start = self.check_range(index, offset, nbytes)?;
+        Ok(self.buffers.at(start))
+    }
 
-        self.push_entry(entry)
+    /// Return the buffer held by `user_data`, if any, to the free list.
+    fn free(&self, user_data: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.in_flight.remove(&user_data) {
+            Some(index) => {
+                state.free.push_back(index);
+                true
+            }
+            None => false,
+        }
     }
+}
 
-    /// Connects to a socket from a client.
-    fn prep_socket_connect(
-        &mut self,
-        _py: Python<'_>,
-        user_data: u64,
-        fd: RawFd,
-        sock_addr: SockAddr,
-    ) -> PyResult<()> {
-        self.pinned_sockaddr.insert(user_data, sock_addr.inner);
+#[pymethods]
+impl BufferPool {
+    /// Take the next free buffer for the op with `user_data`; returns its index.
+    fn acquire(&self, user_data: u64) -> PyResult<u16> {
+        let mut state = self.state.lock().unwrap();
+        if state.in_flight.contains_key(&user_data) {
+            return Err(PyValueError::new_err(format!(
+                "user_data {user_data} already holds a buffer"
+            )));
+        }
+        let index = state
+            .free
+            .pop_front()
+            .ok_or_else(|| RingError::new_err("No free buffer in pool"))?;
+        state.in_flight.insert(user_data, index);
+        Ok(index)
+    }
 
-        let stored = self.pinned_sockaddr.get(&user_data).unwrap();
-        let (ptr, len) = stored.as_ptr_and_len();
+    /// Hand back the buffer held by `user_data` early, e.g. when its op was
+    /// never submitted. Returns whether it held one.
+    fn release(&self, user_data: u64) -> bool {
+        self.free(user_data)
+    }
 
-        let entry = opcode::Connect::new(types::Fd(fd), ptr, len)
-            .build()
-            .user_data(user_data);
+    /// Copy the first `nbytes` of buffer `index`.
+    fn read<'py>(&self, py: Python<'py>, index: u16, nbytes: u32) -> PyResult<Bound<'py, PyBytes>> {
+        let start = self.check_range(index, 0, nbytes)?;
+        self.buffers.to_bytes(py, start, nbytes as usize)
+    }
 
-        self.push_entry(entry)
+    /// Copy `data` into buffer `index` at `offset`, e.g. ahead of a
+    /// `prep_write_fixed` from it. Don't touch a buffer an op is using.
+    #[pyo3(signature = (index, data, offset = 0))]
+    fn write(&self, index: u16, data: &[u8], offset: u32) -> PyResult<()> {
+        let nbytes = u32::try_from(data.len()).unwrap_or(u32::MAX);
+        let ptr = self.buffer_ptr(index, offset, nbytes)?;
+        // SAFETY: the range was checked to lie within buffer `index`. The
+        // memory is only otherwise written by ops reading into it.
+        unsafe { ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len()) };
+        Ok(())
     }
 
-    /// Set socket options.
-    fn prep_socket_setopt(&mut self, user_data: u64, fd: RawFd) -> PyResult<()> {
-        // TODO: Hardcoded for now.
-        let optval = Box::new(1i32); // SO_REUSEADDR value
-        self.pinned_sockopts.insert(user_data, optval);
-        let pinned = self.pinned_sockopts.get(&user_data).unwrap();
+    #[getter]
+    fn count(&self) -> u16 {
+        self.count
+    }
 
-        let entry = opcode::SetSockOpt::new(
-            types::Fd(fd),
-            libc::SOL_SOCKET as u32,
-            libc::SO_REUSEADDR as u32,
-            pinned.as_ref() as *const i32 as *const libc::c_void,
-            std::mem::size_of::<i32>() as u32,
-        )
-        .build()
-        .user_data(user_data);
+    #[getter]
+    fn buf_size(&self) -> u32 {
+        self.buf_size
+    }
 
-        self.push_entry(entry)
+    /// Number of buffers not held by an in-flight op.
+    #[getter]
+    fn available(&self) -> usize {
+        self.state.lock().unwrap().free.len()
     }
 }
 
 //TODO: Move to another module.
-#[derive(Clone)]
 enum SockAddrInner {
     V4(libc::sockaddr_in),
     V6(libc::sockaddr_in6),
@@ -511,10 +4437,14 @@ impl SockAddrInner {
     }
 }
 
+/// A socket address for bind/connect.
+///
+/// Reusing one `SockAddr` for many ops is cheap: they all share its single
+/// copy of the address, which lives until the last of them completes.
 #[pyclass]
 #[derive(Clone)]
 struct SockAddr {
-    inner: SockAddrInner,
+    inner: Arc<SockAddrInner>,
 }
 
 #[pymethods]
@@ -530,7 +4460,7 @@ impl SockAddr {
         addr.sin_addr.s_addr = u32::from_ne_bytes(addr_parsed.octets());
         addr.sin_port = port.to_be();
         Ok(SockAddr {
-            inner: SockAddrInner::V4(addr),
+            inner: Arc::new(SockAddrInner::V4(addr)),
         })
     }
 
@@ -545,11 +4475,187 @@ impl SockAddr {
         addr.sin6_addr.s6_addr = addr_parsed.octets();
         addr.sin6_port = port.to_be();
         Ok(SockAddr {
-            inner: SockAddrInner::V6(addr),
+            inner: Arc::new(SockAddrInner::V6(addr)),
         })
     }
 }
 
+/// `msghdr` for `prep_recvmsg`, owning the source address and control buffers
+/// the kernel fills in.
+#[pyclass]
+struct RecvMsgHeader {
+    msghdr: Box<libc::msghdr>,
+    name: Box<libc::sockaddr_storage>,
+    control: Vec<u8>,
+    iovecs: Vec<libc::iovec>,
+    /// Set while a `prep_recvmsg` using this header hasn't been reaped.
+    in_flight: Arc<AtomicBool>,
+}
+
+// SAFETY: the raw pointers in `msghdr` and `iovecs` only point into memory
+// owned by this struct or pinned alongside it by the Ring.
+unsafe impl Send for RecvMsgHeader {}
+unsafe impl Sync for RecvMsgHeader {}
+
+impl RecvMsgHeader {
+    /// Point the msghdr at `bufs` and our own name/control storage, and
+    /// return it for the kernel. Fails if an earlier op still uses it.
+    fn prepare(&mut self, bufs: &[Bound<'_, PyByteArray>]) -> PyResult<*mut libc::msghdr> {
+        if self.in_flight.swap(true, Ordering::AcqRel) {
+            return Err(PyValueError::new_err(
+                "RecvMsgHeader is in use by an in-flight recvmsg",
+            ));
+        }
+        self.iovecs = bufs
+            .iter()
+            .map(|buf| libc::iovec {
+                iov_base: buf.data().cast(),
+                iov_len: buf.len(),
+            })
+            .collect();
+
+        let msghdr = &mut *self.msghdr;
+        *msghdr = unsafe { std::mem::zeroed() };
+        msghdr.msg_name = (&mut *self.name as *mut libc::sockaddr_storage).cast();
+        msghdr.msg_namelen = std::mem::size_of::<libc::sockaddr_storage>() as u32;
+        msghdr.msg_iov = self.iovecs.as_mut_ptr();
+        msghdr.msg_iovlen = self.iovecs.len();
+        if !self.control.is_empty() {
+            msghdr.msg_control = self.control.as_mut_ptr().cast();
+            msghdr.msg_controllen = self.control.len();
+        }
+        Ok(msghdr)
+    }
+}
+
+#[pymethods]
+impl RecvMsgHeader {
+    #[new]
+    #[pyo3(signature = (control_len = 0))]
+    fn new(control_len: usize) -> Self {
+        RecvMsgHeader {
+            msghdr: Box::new(unsafe { std::mem::zeroed() }),
+            name: Box::new(unsafe { std::mem::zeroed() }),
+            control: vec![0; control_len],
+            iovecs: Vec::new(),
+            in_flight: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Source `(host, port)`, or None if the socket reported no address.
+    #[getter]
+    fn address(&self) -> Option<(String, u16)> {
+        if self.msghdr.msg_namelen == 0 {
+            return None;
+        }
+        match self.name.ss_family as i32 {
+            libc::AF_INET => {
+                // SAFETY: the family says the storage holds a sockaddr_in.
+                let addr = unsafe { &*(&*self.name as *const _ as *const libc::sockaddr_in) };
+                let ip = Ipv4Addr::from(addr.sin_addr.s_addr.to_ne_bytes());
+                Some((ip.to_string(), u16::from_be(addr.sin_port)))
+            }
+            libc::AF_INET6 => {
+                // SAFETY: the family says the storage holds a sockaddr_in6.
+                let addr = unsafe { &*(&*self.name as *const _ as *const libc::sockaddr_in6) };
+                let ip = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+                Some((ip.to_string(), u16::from_be(addr.sin6_port)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Ancillary data received (`cmsghdr` records, as raw bytes).
+    #[getter]
+    fn control<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let len = self.msghdr.msg_controllen.min(self.control.len());
+        PyBytes::new(py, &self.control[..len])
+    }
+
+    /// Flags the kernel set on the received message (e.g. `MSG_TRUNC`).
+    #[getter]
+    fn msg_flags(&self) -> i32 {
+        self.msghdr.msg_flags
+    }
+}
+
+/// Wakes a `Ring` from any thread by posting a CQE to it, which a blocking
+/// `wait()` returns. Get one from `Ring.waker()`.
+#[pyclass(frozen)]
+struct Waker {
+    /// A small ring of its own to send the message from.
+    ring: Mutex<IoUring>,
+    /// A dup of the target ring's fd, so a closed `Ring` can't be mixed up
+    /// with whatever reuses its fd number.
+    target: OwnedFd,
+}
+
+#[pymethods]
+impl Waker {
+    /// Post a CQE with `user_data == WAKE_USER_DATA` and `res == 0` to the
+    /// ring (`IORING_OP_MSG_RING`). Safe to call from any thread, also while
+    /// the ring is in `wait()`. It isn't an op, so `in_flight` is unchanged.
+    fn wake(&self, py: Python<'_>) -> PyResult<()> {
+        let res = py.detach(|| {
+            let mut ring = self.ring.lock().unwrap();
+            let entry = opcode::MsgRingData::new(
+                types::Fd(self.target.as_raw_fd()),
+                0,
+                WAKE_USER_DATA,
+                None,
+            )
+            .build();
+            // SAFETY: the message carries no pointers.
+            unsafe { ring.submission().push(&entry) }
+                .expect("the waker's ring is drained after every wake");
+            ring.submit_and_wait(1)?;
+            Ok::<_, std::io::Error>(ring.completion().next().map_or(0, |cqe| cqe.result()))
+        });
+        match res {
+            Ok(0) => Ok(()),
+            Ok(res) => Err(RingError::new_err(format!(
+                "io_uring msg_ring failed: {}",
+                std::io::Error::from_raw_os_error(-res)
+            ))),
+            Err(e) => Err(RingError::new_err(format!("io_uring_submit failed: {e}"))),
+        }
+    }
+}
+
+/// Connect outcome filled in by `prep_socket_connect_checked`.
+#[pyclass(frozen)]
+struct SockErrorBuffer {
+    /// `SO_ERROR`, written by the kernel.
+    so_error: Box<AtomicI32>,
+    /// Errno the connect itself completed with.
+    connect_errno: AtomicI32,
+}
+
+#[pymethods]
+impl SockErrorBuffer {
+    #[new]
+    fn new() -> Self {
+        SockErrorBuffer {
+            so_error: Box::new(AtomicI32::new(0)),
+            connect_errno: AtomicI32::new(0),
+        }
+    }
+
+    /// The connect error as a positive errno, 0 if the socket connected.
+    ///
+    /// The connect's own failure takes precedence; `SO_ERROR` covers an
+    /// error left pending on the socket.
+    #[getter]
+    fn error(&self) -> i32 {
+        match self.connect_errno.load(Ordering::Acquire) {
+            0 => self.so_error.load(Ordering::Acquire),
+            errno => errno,
+        }
+    }
+}
+
+/// A `struct statx` for `prep_statx` to fill in, decoded field by field once
+/// its CQE is in.
 #[pyclass]
 #[derive(Clone, Debug)]
 struct StatxBuffer {
@@ -584,6 +4690,103 @@ impl StatxBuffer {
     fn mode(&self) -> u32 {
         self.inner.stx_mode as u32
     }
+
+    /// `STATX_*` bits of the fields the kernel filled in; the others read 0.
+    #[getter]
+    fn mask(&self) -> u32 {
+        self.inner.stx_mask
+    }
+
+    #[getter]
+    fn nlink(&self) -> u32 {
+        self.inner.stx_nlink
+    }
+
+    #[getter]
+    fn uid(&self) -> u32 {
+        self.inner.stx_uid
+    }
+
+    #[getter]
+    fn gid(&self) -> u32 {
+        self.inner.stx_gid
+    }
+
+    /// Preferred I/O block size.
+    #[getter]
+    fn blksize(&self) -> u32 {
+        self.inner.stx_blksize
+    }
+
+    /// Allocated 512-byte blocks.
+    #[getter]
+    fn blocks(&self) -> u64 {
+        self.inner.stx_blocks
+    }
+
+    /// `STATX_ATTR_*` flags.
+    #[getter]
+    fn attributes(&self) -> u64 {
+        self.inner.stx_attributes
+    }
+
+    #[getter]
+    fn atime_ns(&self) -> i64 {
+        timestamp_ns(&self.inner.stx_atime)
+    }
+
+    /// Creation time, if the filesystem records one (`STATX_BTIME`).
+    #[getter]
+    fn btime_ns(&self) -> i64 {
+        timestamp_ns(&self.inner.stx_btime)
+    }
+
+    #[getter]
+    fn ctime_ns(&self) -> i64 {
+        timestamp_ns(&self.inner.stx_ctime)
+    }
+
+    #[getter]
+    fn mtime_ns(&self) -> i64 {
+        timestamp_ns(&self.inner.stx_mtime)
+    }
+
+    /// Device the file lives on, as `os.stat().st_dev`.
+    #[getter]
+    fn dev(&self) -> u64 {
+        libc::makedev(self.inner.stx_dev_major, self.inner.stx_dev_minor)
+    }
+
+    /// Device a device file stands for, as `os.stat().st_rdev`.
+    #[getter]
+    fn rdev(&self) -> u64 {
+        libc::makedev(self.inner.stx_rdev_major, self.inner.stx_rdev_minor)
+    }
+}
+
+/// A `statx` timestamp in nanoseconds, as `os.stat().st_mtime_ns`.
+fn timestamp_ns(ts: &libc::statx_timestamp) -> i64 {
+    ts.tv_sec * 1_000_000_000 + ts.tv_nsec as i64
+}
+
+/// Best-effort check that io_uring is usable here (kernel support, not disabled
+/// by `kernel.io_uring_disabled` or a seccomp policy), by setting up and
+/// tearing down a one-entry ring.
+#[pyfunction]
+fn is_supported() -> bool {
+    IoUring::new(1).is_ok()
+}
+
+/// Build an I/O priority from a class (`IOPRIO_CLASS_*`) and a level
+/// (0 highest to 7 lowest), like the kernel's `IOPRIO_PRIO_VALUE`.
+#[pyfunction]
+fn ioprio_value(ioprio_class: u16, level: u16) -> PyResult<u16> {
+    if ioprio_class > IOPRIO_CLASS_IDLE || level > 7 {
+        return Err(PyValueError::new_err(format!(
+            "Invalid ioprio class {ioprio_class} / level {level} (class 0-3, level 0-7)"
+        )));
+    }
+    Ok(ioprio_class << IOPRIO_CLASS_SHIFT | level)
 }
 
 fn register_constants(m: &Bound<'_, PyModule>) -> PyResult<()> {
@@ -596,6 +4799,13 @@ fn register_constants(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add("O_APPEND", libc::O_APPEND)?;
     m.add("O_NONBLOCK", libc::O_NONBLOCK)?;
     m.add("O_CLOEXEC", libc::O_CLOEXEC)?;
+    m.add("O_EXCL", libc::O_EXCL)?;
+    m.add("O_DIRECT", libc::O_DIRECT)?;
+    m.add("O_DIRECTORY", libc::O_DIRECTORY)?;
+    m.add("O_NOFOLLOW", libc::O_NOFOLLOW)?;
+    m.add("O_SYNC", libc::O_SYNC)?;
+    m.add("O_DSYNC", libc::O_DSYNC)?;
+    m.add("O_TMPFILE", libc::O_TMPFILE)?;
 
     // File mode bits (permissions)
     m.add("S_IRUSR", libc::S_IRUSR)?;
@@ -653,6 +4863,94 @@ fn register_constants(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Socket: send/recv flags
     m.add("MSG_NOSIGNAL", libc::MSG_NOSIGNAL)?;
     m.add("MSG_DONTWAIT", libc::MSG_DONTWAIT)?;
+    m.add("MSG_WAITALL", libc::MSG_WAITALL)?;
+    m.add("MSG_PEEK", libc::MSG_PEEK)?;
+    m.add("MSG_MORE", libc::MSG_MORE)?;
+    m.add("MSG_TRUNC", libc::MSG_TRUNC)?;
+    m.add("MSG_CTRUNC", libc::MSG_CTRUNC)?;
+
+    // Reserved user_data (Waker.wake)
+    m.add("WAKE_USER_DATA", WAKE_USER_DATA)?;
+    m.add("MAX_USER_DATA", MAX_USER_DATA)?;
+
+    // Cancel flags (prep_cancel)
+    m.add("IORING_ASYNC_CANCEL_ALL", IORING_ASYNC_CANCEL_ALL)?;
+    m.add("IORING_ASYNC_CANCEL_FD", IORING_ASYNC_CANCEL_FD)?;
+    m.add("IORING_ASYNC_CANCEL_ANY", IORING_ASYNC_CANCEL_ANY)?;
+    m.add("IORING_ASYNC_CANCEL_USERDATA", IORING_ASYNC_CANCEL_USERDATA)?;
+    m.add("IORING_ASYNC_CANCEL_OP", IORING_ASYNC_CANCEL_OP)?;
+
+    // Timeout flags (prep_timeouts)
+    m.add("IORING_TIMEOUT_ABS", types::TimeoutFlags::ABS.bits())?;
+    m.add(
+        "IORING_TIMEOUT_BOOTTIME",
+        types::TimeoutFlags::BOOTTIME.bits(),
+    )?;
+    m.add(
+        "IORING_TIMEOUT_REALTIME",
+        types::TimeoutFlags::REALTIME.bits(),
+    )?;
+    m.add(
+        "IORING_TIMEOUT_ETIME_SUCCESS",
+        types::TimeoutFlags::ETIME_SUCCESS.bits(),
+    )?;
+
+    // Poll events
+    m.add("POLLIN", libc::POLLIN)?;
+    m.add("POLLPRI", libc::POLLPRI)?;
+    m.add("POLLOUT", libc::POLLOUT)?;
+    m.add("POLLERR", libc::POLLERR)?;
+    m.add("POLLHUP", libc::POLLHUP)?;
+    m.add("POLLRDHUP", libc::POLLRDHUP)?;
+
+    // File and memory advice (prep_fadvise, prep_madvise)
+    m.add("POSIX_FADV_NORMAL", libc::POSIX_FADV_NORMAL)?;
+    m.add("POSIX_FADV_SEQUENTIAL", libc::POSIX_FADV_SEQUENTIAL)?;
+    m.add("POSIX_FADV_RANDOM", libc::POSIX_FADV_RANDOM)?;
+    m.add("POSIX_FADV_NOREUSE", libc::POSIX_FADV_NOREUSE)?;
+    m.add("POSIX_FADV_WILLNEED", libc::POSIX_FADV_WILLNEED)?;
+    m.add("POSIX_FADV_DONTNEED", libc::POSIX_FADV_DONTNEED)?;
+    m.add("MADV_NORMAL", libc::MADV_NORMAL)?;
+    m.add("MADV_RANDOM", libc::MADV_RANDOM)?;
+    m.add("MADV_SEQUENTIAL", libc::MADV_SEQUENTIAL)?;
+    m.add("MADV_WILLNEED", libc::MADV_WILLNEED)?;
+    m.add("MADV_DONTNEED", libc::MADV_DONTNEED)?;
+
+    // Sync flags (prep_sync_file_range)
+    m.add(
+        "SYNC_FILE_RANGE_WAIT_BEFORE",
+        libc::SYNC_FILE_RANGE_WAIT_BEFORE,
+    )?;
+    m.add("SYNC_FILE_RANGE_WRITE", libc::SYNC_FILE_RANGE_WRITE)?;
+    m.add(
+        "SYNC_FILE_RANGE_WAIT_AFTER",
+        libc::SYNC_FILE_RANGE_WAIT_AFTER,
+    )?;
+
+    // Xattr flags (prep_setxattr, prep_fsetxattr)
+    m.add("XATTR_CREATE", libc::XATTR_CREATE)?;
+    m.add("XATTR_REPLACE", libc::XATTR_REPLACE)?;
+
+    // Per-op read/write flags (rw_flags)
+    m.add("RWF_HIPRI", libc::RWF_HIPRI)?;
+    m.add("RWF_DSYNC", libc::RWF_DSYNC)?;
+    m.add("RWF_SYNC", libc::RWF_SYNC)?;
+    m.add("RWF_NOWAIT", libc::RWF_NOWAIT)?;
+    m.add("RWF_APPEND", libc::RWF_APPEND)?;
+
+    // Write lifetime hints (write_hint)
+    m.add("RWH_WRITE_LIFE_NOT_SET", RWH_WRITE_LIFE_NOT_SET)?;
+    m.add("RWH_WRITE_LIFE_NONE", RWH_WRITE_LIFE_NONE)?;
+    m.add("RWH_WRITE_LIFE_SHORT", RWH_WRITE_LIFE_SHORT)?;
+    m.add("RWH_WRITE_LIFE_MEDIUM", RWH_WRITE_LIFE_MEDIUM)?;
+    m.add("RWH_WRITE_LIFE_LONG", RWH_WRITE_LIFE_LONG)?;
+    m.add("RWH_WRITE_LIFE_EXTREME", RWH_WRITE_LIFE_EXTREME)?;
+
+    // I/O priority classes (ioprio_value)
+    m.add("IOPRIO_CLASS_NONE", IOPRIO_CLASS_NONE)?;
+    m.add("IOPRIO_CLASS_RT", IOPRIO_CLASS_RT)?;
+    m.add("IOPRIO_CLASS_BE", IOPRIO_CLASS_BE)?;
+    m.add("IOPRIO_CLASS_IDLE", IOPRIO_CLASS_IDLE)?;
 
     // Signals
     m.add("SIGINT", libc::SIGINT)?;
@@ -663,6 +4961,31 @@ fn register_constants(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add("SFD_NONBLOCK", libc::SFD_NONBLOCK)?;
     m.add("SFD_CLOEXEC", libc::SFD_CLOEXEC)?;
 
+    // Socket commands (prep_uring_cmd)
+    m.add("SOCKET_URING_OP_SIOCINQ", SOCKET_URING_OP_SIOCINQ)?;
+    m.add("SOCKET_URING_OP_SIOCOUTQ", SOCKET_URING_OP_SIOCOUTQ)?;
+    m.add("SOCKET_URING_OP_GETSOCKOPT", SOCKET_URING_OP_GETSOCKOPT)?;
+    m.add("SOCKET_URING_OP_SETSOCKOPT", SOCKET_URING_OP_SETSOCKOPT)?;
+
+    // io_uring opcodes (for register_restrictions)
+    m.add("IORING_OP_NOP", opcode::Nop::CODE)?;
+    m.add("IORING_OP_READ", opcode::Read::CODE)?;
+    m.add("IORING_OP_WRITE", opcode::Write::CODE)?;
+    m.add("IORING_OP_OPENAT", opcode::OpenAt::CODE)?;
+    m.add("IORING_OP_CLOSE", opcode::Close::CODE)?;
+    m.add("IORING_OP_STATX", opcode::Statx::CODE)?;
+    m.add("IORING_OP_TIMEOUT", opcode::Timeout::CODE)?;
+    m.add("IORING_OP_POLL_ADD", opcode::PollAdd::CODE)?;
+    m.add("IORING_OP_ASYNC_CANCEL", opcode::AsyncCancel::CODE)?;
+    m.add("IORING_OP_SOCKET", opcode::Socket::CODE)?;
+    m.add("IORING_OP_RECV", opcode::Recv::CODE)?;
+    m.add("IORING_OP_SEND", opcode::Send::CODE)?;
+    m.add("IORING_OP_BIND", opcode::Bind::CODE)?;
+    m.add("IORING_OP_LISTEN", opcode::Listen::CODE)?;
+    m.add("IORING_OP_ACCEPT", opcode::Accept::CODE)?;
+    m.add("IORING_OP_CONNECT", opcode::Connect::CODE)?;
+    m.add("IORING_OP_URING_CMD", opcode::UringCmd16::CODE)?;
+
     Ok(())
 }
 
@@ -672,6 +4995,32 @@ fn _rusty_ring(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<CompletionEvent>()?;
     m.add_class::<SockAddr>()?;
     m.add_class::<StatxBuffer>()?;
+    m.add_class::<SockErrorBuffer>()?;
+    m.add_class::<BufferPool>()?;
+    m.add_class::<RecvMsgHeader>()?;
+    m.add_class::<Chain>()?;
+    m.add_class::<Waker>()?;
+    m.add_class::<RingParams>()?;
+    m.add_function(wrap_pyfunction!(is_supported, m)?)?;
+    m.add_function(wrap_pyfunction!(ioprio_value, m)?)?;
+    m.add("RingError", m.py().get_type::<RingError>())?;
+    m.add(
+        "SubmissionQueueFull",
+        m.py().get_type::<SubmissionQueueFull>(),
+    )?;
+    m.add(
+        "CompletionQueueFull",
+        m.py().get_type::<CompletionQueueFull>(),
+    )?;
+    m.add(
+        "RingNotInitialized",
+        m.py().get_type::<RingNotInitialized>(),
+    )?;
+    m.add(
+        "RingCompletionError",
+        m.py().get_type::<RingCompletionError>(),
+    )?;
+    m.add("RingSetupError", ring_setup_error_type(m.py())?)?;
 
     register_constants(m)?;
     Ok(())
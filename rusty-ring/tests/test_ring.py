@@ -1,10 +1,66 @@
+import array
+import asyncio
+import contextlib
+import errno
+import fcntl
+import mmap
+import os
+import signal
+import socket
+import subprocess
+import sys
 import threading
 import time
+import weakref
 from concurrent.futures import ThreadPoolExecutor
 from typing import TYPE_CHECKING
 
+import pytest
+
 from one_ring_loop.log import get_logger
-from rusty_ring import Ring
+from rusty_ring import (
+    AF_INET,
+    AT_FDCWD,
+    IOPRIO_CLASS_BE,
+    IORING_ASYNC_CANCEL_ALL,
+    IORING_OP_NOP,
+    IORING_OP_TIMEOUT,
+    IORING_TIMEOUT_ABS,
+    IORING_TIMEOUT_ETIME_SUCCESS,
+    MADV_WILLNEED,
+    MAX_USER_DATA,
+    MSG_PEEK,
+    MSG_TRUNC,
+    O_CREAT,
+    O_RDONLY,
+    POLLIN,
+    POLLOUT,
+    POSIX_FADV_WILLNEED,
+    RWF_NOWAIT,
+    RWH_WRITE_LIFE_LONG,
+    RWH_WRITE_LIFE_SHORT,
+    SOCK_CLOEXEC,
+    SOCK_STREAM,
+    SOCKET_URING_OP_SIOCINQ,
+    STATX_ALL,
+    SYNC_FILE_RANGE_WAIT_AFTER,
+    SYNC_FILE_RANGE_WRITE,
+    WAKE_USER_DATA,
+    XATTR_CREATE,
+    CompletionQueueFull,
+    RecvMsgHeader,
+    Ring,
+    RingError,
+    RingNotInitialized,
+    RingSetupError,
+    SockAddr,
+    SockErrorBuffer,
+    StatxBuffer,
+    SubmissionQueueFull,
+    ioprio_value,
+    is_supported,
+    socket_from_fd,
+)
 
 if TYPE_CHECKING:
     from pathlib import Path
@@ -12,6 +68,12 @@ if TYPE_CHECKING:
 logger = get_logger(__name__)
 
 SERVER_MESSAGE = b"A new client connected!"
+# Past any fd table, so ops on it fail with EBADF.
+UNUSED_FD = 1 << 20
+
+
+def test_is_supported() -> None:
+    assert is_supported()
 
 
 class TestRing:
@@ -19,6 +81,81 @@ class TestRing:
         with Ring(32) as ring:
             assert isinstance(ring, Ring)
 
+    def test_ring_geometry(self) -> None:
+        ring = Ring(100)
+        assert not ring.is_open
+
+        with ring:
+            assert ring.is_open
+            assert ring.depth == 100
+            assert ring.sq_entries == 128
+            assert ring.cq_entries == 256
+
+        assert not ring.is_open
+
+    def test_params_reports_setup(self) -> None:
+        # IORING_SETUP_CQE32 and IORING_FEAT_NODROP.
+        with Ring(8, cqe32=True) as ring:
+            params = ring.params()
+            assert (params.sq_entries, params.cq_entries) == (8, 16)
+            assert params.flags & 1 << 11
+            assert params.features & 1 << 1
+            assert params.sq_off["head"] == 0
+            assert params.cq_off["cqes"] > params.cq_off["ring_entries"]
+
+    def test_statx_matches_os_stat(self, tmp_file_path: Path) -> None:
+        tmp_file_path.write_bytes(b"x" * 1000)
+        statx = StatxBuffer()
+        with Ring(32) as ring:
+            ring.prep_statx(0, str(tmp_file_path), statx, 0, STATX_ALL, AT_FDCWD)
+            ring.submit()
+            assert ring.wait().res == 0
+
+        st = tmp_file_path.stat()
+        assert statx.mask & STATX_ALL == STATX_ALL
+        assert (statx.size, statx.ino, statx.mode) == (1000, st.st_ino, st.st_mode)
+        assert (statx.uid, statx.gid, statx.nlink) == (st.st_uid, st.st_gid, 1)
+        assert statx.mtime_ns == st.st_mtime_ns
+        assert statx.dev == st.st_dev
+        assert statx.blocks == st.st_blocks
+
+    def test_xattr_set_then_get(self, tmp_file_path: Path) -> None:
+        tmp_file_path.touch()
+        path = str(tmp_file_path)
+        with Ring(32) as ring:
+            ring.prep_setxattr(0, path, "user.sha256", b"abc123")
+            ring.submit()
+            res = ring.wait().res
+            if res == -errno.EOPNOTSUPP:
+                pytest.skip("filesystem has no user xattrs")
+            assert res == 0
+
+            value = bytearray(16)
+            ring.prep_getxattr(1, path, "user.sha256", value)
+            ring.prep_getxattr(2, path, "user.sha256", bytearray(2))
+            ring.prep_setxattr(3, path, "user.sha256", b"", XATTR_CREATE)
+            events = {event.user_data: event for event in ring.submit_wait_drain(3)}
+            assert events[1].res == 6
+            assert value[:6] == b"abc123"
+            assert events[2].res == -errno.ERANGE
+            assert events[3].res == -errno.EEXIST
+
+    def test_fd_xattr_set_then_get(self, tmp_file_path: Path) -> None:
+        with Ring(32) as ring, tmp_file_path.open("w+b") as f:
+            ring.prep_fsetxattr(0, f.fileno(), "user.sha256", b"def456")
+            ring.submit()
+            res = ring.wait().res
+            if res == -errno.EOPNOTSUPP:
+                pytest.skip("filesystem has no user xattrs")
+            assert res == 0
+
+            value = bytearray(16)
+            ring.prep_fgetxattr(1, f.fileno(), "user.sha256", value)
+            ring.submit()
+            assert ring.wait().res == 6
+            assert value[:6] == b"def456"
+            assert os.getxattr(tmp_file_path, "user.sha256") == b"def456"
+
     def test_file_open_write_read(self, tmp_file_path: Path) -> None:
         file_content = b"Hello! :)"
 
@@ -44,6 +181,287 @@ class TestRing:
             logger.info("Got read event", io_event=read_event)
             assert bytes(read_buf) == file_content
 
+    def test_open_with_registered_personality(self, tmp_file_path: Path) -> None:
+        with Ring(32) as ring:
+            personality = ring.register_personality()
+            ring.prep_openat(
+                0,
+                str(tmp_file_path),
+                os.O_CREAT | os.O_RDWR,
+                0o600,
+                -100,
+                personality=personality,
+            )
+            ring.submit()
+            fd = ring.wait().res
+            assert fd >= 0
+            os.close(fd)
+
+    def test_write_hint_is_set_on_file(self, tmp_file_path: Path) -> None:
+        f_get_rw_hint = 1035
+        with Ring(32) as ring, tmp_file_path.open("w+b") as f:
+            ring.prep_write(0, f.fileno(), b"log", 0, write_hint=RWH_WRITE_LIFE_SHORT)
+            ring.submit()
+            assert ring.wait().res == 3
+            hint = fcntl.fcntl(f.fileno(), f_get_rw_hint, bytes(8))
+            assert int.from_bytes(hint, sys.byteorder) == RWH_WRITE_LIFE_SHORT
+
+            with pytest.raises(ValueError, match="write hint"):
+                ring.prep_write(1, f.fileno(), b"", 0, write_hint=42)
+
+            pool = ring.register_buffer_pool(count=1, buf_size=16)
+            index = pool.acquire(2)
+            pool.write(index, b"fixed")
+            ring.prep_write_fixed(
+                2, f.fileno(), index, 5, 3, write_hint=RWH_WRITE_LIFE_LONG
+            )
+            ring.submit()
+            assert ring.wait().res == 5
+            hint = fcntl.fcntl(f.fileno(), f_get_rw_hint, bytes(8))
+            assert int.from_bytes(hint, sys.byteorder) == RWH_WRITE_LIFE_LONG
+
+    def test_append_write_ignores_offset(self, tmp_file_path: Path) -> None:
+        with Ring(32) as ring, tmp_file_path.open("w+b") as f:
+            ring.prep_write(0, f.fileno(), b"head", 0)
+            ring.submit()
+            assert ring.wait().res == 4
+            ring.prep_write(1, f.fileno(), b"tail", 0, append=True)
+            ring.submit()
+            assert ring.wait().res == 4
+        assert tmp_file_path.read_bytes() == b"headtail"
+
+    def test_durable_append_syncs_the_written_range(self, tmp_file_path: Path) -> None:
+        with Ring(32) as ring, tmp_file_path.open("w+b") as f:
+            with ring.chain():
+                ring.prep_write(1, f.fileno(), b"record", 0)
+                ring.prep_sync_file_range(2, f.fileno(), 0, 6, SYNC_FILE_RANGE_WRITE)
+            ring.prep_sync_file_range(3, f.fileno(), 0, 6, SYNC_FILE_RANGE_WAIT_AFTER)
+            ring.prep_fsync(4, f.fileno(), datasync=True)
+            ring.prep_fsync(5, f.fileno())
+            ring.prep_sync_file_range(6, f.fileno(), flags=1 << 7)
+            events = {event.user_data: event for event in ring.submit_wait_drain(6)}
+            assert events[1].res == 6
+            assert [events[n].res for n in (2, 3, 4, 5)] == [0, 0, 0, 0]
+            assert events[6].res == -errno.EINVAL
+            assert tmp_file_path.read_bytes() == b"record"
+
+    def test_fadvise_result_is_unmodified(self, tmp_file_path: Path) -> None:
+        read_fd, write_fd = os.pipe()
+        with Ring(32) as ring, tmp_file_path.open("w+b") as f:
+            ring.prep_fadvise(1, f.fileno(), 0, 0, POSIX_FADV_WILLNEED)
+            ring.prep_fadvise(2, f.fileno(), 0, 0, 1234)
+            ring.prep_fadvise(3, read_fd, 0, 0, POSIX_FADV_WILLNEED)
+            events = {event.user_data: event for event in ring.submit_wait_drain(3)}
+            assert events[1].res == 0
+            assert events[2].res == -errno.EINVAL
+            assert events[3].res == -errno.ESPIPE
+        os.close(read_fd)
+        os.close(write_fd)
+
+    def test_madvise_checks_alignment(self) -> None:
+        with Ring(32) as ring, mmap.mmap(-1, 2 * mmap.PAGESIZE) as region:
+            ring.prep_madvise(1, region, MADV_WILLNEED)
+            ring.prep_madvise(2, region, MADV_WILLNEED, offset=1, length=16)
+            events = {event.user_data: event for event in ring.submit_wait_drain(2)}
+            assert events[1].res == 0
+            assert events[2].res == -errno.EINVAL
+
+            with pytest.raises(ValueError, match="out of bounds"):
+                ring.prep_madvise(3, region, MADV_WILLNEED, length=3 * mmap.PAGESIZE)
+
+    def test_openat_defaults_to_cwd(self, tmp_file_path: Path) -> None:
+        tmp_file_path.write_bytes(b"")
+        with Ring(32) as ring:
+            ring.prep_openat(0, os.path.relpath(tmp_file_path), O_RDONLY)
+            ring.submit()
+            fd = ring.wait().res
+            assert fd >= 0
+            os.close(fd)
+
+    def test_openat_validates_mode(self, tmp_file_path: Path) -> None:
+        with Ring(32) as ring:
+            with pytest.raises(ValueError, match="permission bits"):
+                ring.prep_openat(0, str(tmp_file_path), O_CREAT, 0o100644, AT_FDCWD)
+            with pytest.warns(UserWarning, match="mode is ignored"):
+                ring.prep_openat(1, str(tmp_file_path), O_RDONLY, 0o644, AT_FDCWD)
+
+    def test_read_write_with_ioprio(self, tmp_file_path: Path) -> None:
+        ioprio = ioprio_value(IOPRIO_CLASS_BE, 0)
+        with Ring(32) as ring, tmp_file_path.open("w+b") as f:
+            ring.prep_write(0, f.fileno(), b"prio", 0, ioprio=ioprio)
+            ring.submit()
+            assert ring.wait().res == 4
+
+            buf = bytearray(4)
+            ring.prep_read(1, f.fileno(), buf, 4, 0, ioprio=ioprio)
+            ring.submit()
+            assert ring.wait().res == 4
+            assert buf == b"prio"
+
+    def test_offset_minus_one_uses_file_position(self) -> None:
+        read_fd, write_fd = os.pipe()
+        try:
+            with Ring(32) as ring:
+                ring.prep_write(0, write_fd, b"abcd", -1)
+                ring.submit()
+                assert ring.wait().res == 4
+
+                for user_data, expected in enumerate((b"ab", b"cd")):
+                    buf = bytearray(2)
+                    ring.prep_read(user_data, read_fd, buf, 2, -1)
+                    ring.submit()
+                    assert ring.wait().res == 2
+                    assert buf == expected
+
+                with pytest.raises(ValueError, match="offset"):
+                    ring.prep_read(3, read_fd, bytearray(2), 2, -2)
+        finally:
+            os.close(read_fd)
+            os.close(write_fd)
+
+    def test_nowait_read_on_empty_pipe_returns_eagain(self) -> None:
+        read_fd, write_fd = os.pipe()
+        try:
+            with Ring(32) as ring:
+                ring.prep_read(0, read_fd, bytearray(4), 4, 0, rw_flags=RWF_NOWAIT)
+                ring.submit()
+                assert ring.wait().res == -errno.EAGAIN
+        finally:
+            os.close(read_fd)
+            os.close(write_fd)
+
+    def test_reads_into_disjoint_regions_of_one_buffer(
+        self, tmp_file_path: Path
+    ) -> None:
+        tmp_file_path.write_bytes(b"headtail")
+        buf = bytearray(8)
+        with Ring(32) as ring, tmp_file_path.open("rb") as f:
+            ring.prep_read(0, f.fileno(), buf, 4, 4, buf_offset=4)
+            ring.prep_read(1, f.fileno(), buf, 4, 0)
+            ring.submit()
+            assert [event.res for event in (ring.wait(), ring.wait())] == [4, 4]
+
+        assert buf == b"headtail"
+
+    def test_ioprio_value_rejects_bad_class(self) -> None:
+        with pytest.raises(ValueError, match="class"):
+            ioprio_value(4, 0)
+
+    def test_unsafe_push_raw_sqe(self) -> None:
+        with Ring(32) as ring:
+            # IORING_OP_NOP with user_data 7.
+            sqe = bytearray(64)
+            sqe[0] = IORING_OP_NOP
+            sqe[32:40] = (7).to_bytes(8, "little")
+            ring.unsafe_push_raw_sqe(bytes(sqe))
+            ring.submit()
+            assert ring.wait().user_data == 7
+
+            with pytest.raises(ValueError, match="64 or 128 bytes"):
+                ring.unsafe_push_raw_sqe(b"short")
+            with pytest.raises(ValueError, match="sqe128"):
+                ring.unsafe_push_raw_sqe(bytes(sqe) + bytes(64))
+
+    def test_sqe128_ring_takes_both_sqe_sizes(self) -> None:
+        with Ring(32, sqe128=True) as ring:
+            sqe = bytearray(128)
+            sqe[0] = IORING_OP_NOP
+            sqe[32:40] = (7).to_bytes(8, "little")
+            ring.unsafe_push_raw_sqe(bytes(sqe))
+            ring.prep_nop(8)
+            events = ring.submit_wait_drain(2)
+            assert sorted(event.user_data for event in events) == [7, 8]
+
+    def test_async_context_manager(self) -> None:
+        async def main() -> None:
+            async with contextlib.AsyncExitStack() as stack:
+                ring = await stack.enter_async_context(Ring(32))
+                ring.prep_nop(0)
+                ring.submit()
+                assert ring.wait().user_data == 0
+            assert not ring.is_open
+
+        asyncio.run(main())
+
+    def test_recv_poll_first_waits_for_data(self) -> None:
+        receiver, sender = socket.socketpair()
+        with receiver, sender, Ring(32) as ring:
+            buf = bytearray(8)
+            ring.prep_socket_recv(0, receiver.fileno(), buf, poll_first=True)
+            ring.submit()
+            assert ring.peek() is None
+
+            ring.prep_socket_send(1, sender.fileno(), b"ping", poll_first=True)
+            events = {event.user_data: event.res for event in ring.submit_wait_drain(2)}
+            assert events == {0: 4, 1: 4}
+            assert bytes(buf[:4]) == b"ping"
+
+    def test_wait_async_yields_to_the_loop(self) -> None:
+        receiver, sender = socket.socketpair()
+
+        async def main() -> list[int]:
+            with receiver, sender, Ring(32) as ring:
+                ring.prep_socket_recv(0, receiver.fileno(), bytearray(8))
+                asyncio.get_running_loop().call_later(0.05, sender.send, b"ping")
+                events = await ring.wait_async()
+                ring.prep_nop(1)
+                events += await ring.wait_async()
+                return [event.res for event in events]
+
+        assert asyncio.run(main()) == [4, 0]
+
+    def test_big_cqe_only_on_cqe32_rings(self) -> None:
+        for cqe32, big_cqe in ((False, None), (True, (0, 0))):
+            with Ring(32, cqe32=cqe32) as ring:
+                ring.prep_nop(0)
+                ring.submit()
+                event = ring.wait()
+                assert event.res == 0
+                assert event.big_cqe == big_cqe
+
+    def test_chain_links_all_but_last(self) -> None:
+        with Ring(32) as ring:
+            with ring.chain() as c:
+                c.prep_read(0, UNUSED_FD, bytearray(4), 4, 0)
+                c.prep_nop(1)
+            ring.prep_nop(2)
+            ring.submit()
+            waited = (ring.wait(), ring.wait(), ring.wait())
+            events = {event.user_data: event.res for event in waited}
+
+            assert events[0] == -errno.EBADF
+            assert events[1] == -errno.ECANCELED
+            assert events[2] == 0
+
+    def test_negative_fd_is_rejected(self) -> None:
+        with Ring(32) as ring:
+            with pytest.raises(ValueError, match="prep_read: fd -1 is negative"):
+                ring.prep_read(0, -1, bytearray(4), 4, 0)
+            with pytest.raises(ValueError, match="prep_cancel: fd -1"):
+                ring.prep_cancel(1, 0, fd=-1)
+            assert ring.sq_pending() == 0
+
+    def test_reserved_user_data_is_rejected(self) -> None:
+        with Ring(32) as ring:
+            for user_data in (MAX_USER_DATA + 1, 1 << 63, WAKE_USER_DATA):
+                with pytest.raises(ValueError, match="is reserved"):
+                    ring.prep_nop(user_data)
+            with pytest.raises(ValueError, match="is reserved"):
+                ring.prep_timeout(MAX_USER_DATA + 1, sec=1, nsec=0)
+            assert ring.sq_pending() == 0
+
+            ring.prep_nop(MAX_USER_DATA)
+            ring.submit()
+            assert ring.wait().user_data == MAX_USER_DATA
+
+    def test_chain_is_dropped_on_error(self) -> None:
+        with Ring(32) as ring:
+            with pytest.raises(KeyError), ring.chain() as c:
+                c.prep_nop(0)
+                raise KeyError
+            assert len(ring) == 0
+            assert ring.submit() == 0
+
     def test_timeout(self, timing) -> None:
         with Ring(32) as ring:
             sleep_for_sec = 1
@@ -55,6 +473,66 @@ class TestRing:
             ring.wait()
             timing.assert_elapsed_between(1.5, 1.6, msg="Should sleep for 2 secounds")
 
+    def test_timeout_absolute_realtime_deadline(self) -> None:
+        with Ring(32) as ring:
+            deadline = time.clock_gettime_ns(time.CLOCK_REALTIME) + int(1e7)
+            sec, nsec = divmod(deadline, int(1e9))
+            ring.prep_timeout(1, sec, nsec, clock=time.CLOCK_REALTIME, absolute=True)
+            ring.prep_timeout(2, 0, int(1e6), clock=time.CLOCK_BOOTTIME)
+            ring.submit()
+            assert [ring.wait().user_data, ring.wait().user_data] == [2, 1]
+
+    def test_wait_until_stops_at_count_or_deadline(self, timing) -> None:
+        with Ring(32) as ring:
+            ring.prep_nop(1)
+            ring.prep_nop(2)
+            far = time.clock_gettime_ns(time.CLOCK_REALTIME) + int(10e9)
+            sec, nsec = divmod(far, int(1e9))
+            events = ring.wait_until(2, sec, nsec, clock=time.CLOCK_REALTIME)
+            assert sorted(event.user_data for event in events) == [1, 2]
+            assert ring.in_flight == 0
+
+            ring.prep_nop(3)
+            ring.prep_timeout(4, 10, 0)
+            timing.start()
+            soon = time.clock_gettime_ns(time.CLOCK_MONOTONIC) + int(5e7)
+            events = ring.wait_until(2, *divmod(soon, int(1e9)))
+            timing.assert_elapsed_between(0.04, 0.5, msg="Should stop at the deadline")
+            assert [event.user_data for event in events] == [3]
+            assert ring.in_flight == 1
+
+    def test_timeout_rejects_unknown_clock(self) -> None:
+        with Ring(32) as ring, pytest.raises(ValueError, match="Timeout clock"):
+            ring.prep_timeout(1, 0, 0, clock=time.CLOCK_PROCESS_CPUTIME_ID)
+
+    def test_prep_timeouts_arms_a_batch(self) -> None:
+        with Ring(32) as ring:
+            deadline = time.clock_gettime_ns(time.CLOCK_MONOTONIC) + int(1e7)
+            ring.prep_timeouts(
+                [
+                    (1, 0, int(1e6), 0),
+                    (2, 0, int(2e6), IORING_TIMEOUT_ETIME_SUCCESS),
+                    (3, deadline // 10**9, deadline % 10**9, IORING_TIMEOUT_ABS),
+                ]
+            )
+            assert ring.in_flight == 3
+            events = {event.user_data: event for event in ring.submit_wait_drain(3)}
+            assert [events[i].res for i in (1, 2, 3)] == [-errno.ETIME] * 3
+
+            with pytest.raises(ValueError, match="user_data 5"):
+                ring.prep_timeouts([(4, 1, 0, 0), (5, 1, 0, 1 << 20)])
+            assert ring.sq_pending() == 0
+
+    def test_waker_ends_a_wait_from_another_thread(self) -> None:
+        with Ring(32) as ring:
+            waker = ring.waker()
+            timer = threading.Timer(0.05, waker.wake)
+            timer.start()
+            event = ring.wait()
+            timer.join()
+            assert (event.user_data, event.res) == (WAKE_USER_DATA, 0)
+            assert ring.in_flight == 0
+
     def test_wait_does_not_hold_gil(self) -> None:
         flag = threading.Event()
 
@@ -75,3 +553,969 @@ class TestRing:
             logger.info("Finished waiting")
 
             assert flag.is_set()
+
+    def test_restrictions_reject_unlisted_opcode(self) -> None:
+        with Ring(32, r_disabled=True) as ring:
+            ring.register_restrictions([IORING_OP_NOP])
+            ring.enable()
+
+            ring.prep_nop(user_data=1)
+            ring.prep_timeout(user_data=2, sec=0, nsec=0)
+            ring.submit()
+
+            events = {event.user_data: event for event in (ring.wait(), ring.wait())}
+            assert events[1].res == 0
+            assert events[2].res == -errno.EACCES
+
+    def test_timestamps_record_observation_time(self) -> None:
+        with Ring(32, timestamps=True) as ring:
+            before = time.monotonic_ns()
+            ring.prep_nop(user_data=1)
+            ring.submit()
+            event = ring.wait()
+
+            assert event.completed_at_ns is not None
+            assert before <= event.completed_at_ns <= time.monotonic_ns()
+
+        with Ring(32) as ring:
+            ring.prep_nop(user_data=1)
+            ring.submit()
+            assert ring.wait().completed_at_ns is None
+
+    def test_recv_multishot_bundle_fills_several_buffers(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            ring.register_buf_ring(group_id=1, entries=8, buf_size=16)
+            client.sendall(b"x" * 40)
+
+            ring.prep_socket_recv_multishot(1, server.fileno(), group_id=1, bundle=True)
+            ring.submit()
+            event = ring.wait()
+
+            assert event.res > 16
+            assert event.buffer_ids == list(range(-(-event.res // 16)))
+            assert ring.read_buffer(1, 1, 16) == b"x" * 16
+
+    def test_read_multishot_tails_a_pipe(self) -> None:
+        read_fd, write_fd = os.pipe()
+        with Ring(32) as ring:
+            ring.register_buf_ring(group_id=1, entries=4, buf_size=16)
+            ring.prep_read_multishot(1, read_fd, group_id=1)
+            ring.submit()
+
+            os.write(write_fd, b"first")
+            event = ring.wait()
+            assert (event.res, event.more) == (5, True)
+            assert ring.read_buffer(1, event.buffer_ids[0], 5) == b"first"
+
+            os.write(write_fd, b"second")
+            event = ring.wait()
+            assert (event.res, event.more) == (6, True)
+            assert ring.read_buffer(1, event.buffer_ids[0], 6) == b"second"
+
+            os.close(write_fd)
+            event = ring.wait()
+            assert (event.res, event.more) == (0, False)
+            assert ring.in_flight == 0
+        os.close(read_fd)
+
+    def test_recv_from_buffer_group(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            ring.register_buf_ring(group_id=1, entries=4, buf_size=16)
+            client.sendall(b"hello")
+
+            ring.prep_socket_recv(1, server.fileno(), buf_group=1)
+            ring.submit()
+            event = ring.wait()
+
+            assert event.res == 5
+            assert event.buffer_ids == [0]
+            assert ring.read_buffer(1, 0, 5) == b"hello"
+
+    def test_recycled_buffer_is_reused(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            ring.register_buf_ring(group_id=1, entries=1, buf_size=16)
+            client.sendall(b"one")
+            ring.prep_socket_recv(1, server.fileno(), buf_group=1)
+            assert ring.submit_wait_drain()[0].buffer_ids == [0]
+
+            ring.prep_socket_recv(2, server.fileno(), buf_group=1)
+            event = ring.submit_wait_drain()[0]
+            assert event.res == -errno.ENOBUFS
+            assert event.out_of_buffers
+
+            ring.recycle_buffer(1, 0)
+            client.sendall(b"two")
+            ring.prep_socket_recv(3, server.fileno(), buf_group=1)
+            event = ring.submit_wait_drain()[0]
+            assert ring.read_buffer(1, 0, event.res) == b"two"
+
+            ring.recycle_buffer(1, 0)
+            with pytest.raises(ValueError, match="not consumed"):
+                ring.recycle_buffer(1, 0)
+
+    def test_truncated_datagram_consumes_one_buffer(self) -> None:
+        receiver, sender = socket.socketpair(socket.AF_UNIX, socket.SOCK_DGRAM)
+        with receiver, sender, Ring(32) as ring:
+            ring.register_buf_ring(group_id=1, entries=4, buf_size=8)
+            sender.send(b"x" * 32)
+            ring.prep_socket_recv(1, receiver.fileno(), None, MSG_TRUNC, buf_group=1)
+            event = ring.submit_wait_drain()[0]
+            assert event.res == 32
+            assert event.buffer_ids == [0]
+
+            ring.recycle_buffer(1, 0)
+            with pytest.raises(ValueError, match="not consumed"):
+                ring.recycle_buffer(1, 1)
+
+    def test_recv_needs_buf_or_buf_group(self) -> None:
+        with Ring(32) as ring, pytest.raises(ValueError, match="exactly one"):
+            ring.prep_socket_recv(1, 0)
+
+    def test_send_bundle_sends_all_buffers(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            bufs = [b"one ", b"two ", b"three"]
+            ring.prep_socket_send_bundle(0, client.fileno(), bufs)
+            ring.submit()
+            assert ring.wait().res == 13
+            assert server.recv(64) == b"one two three"
+
+    def test_send_all_resubmits_short_sends(self) -> None:
+        server, client = socket.socketpair()
+        payload = os.urandom(1 << 20)
+        received = bytearray()
+
+        def drain() -> None:
+            while len(received) < len(payload):
+                received.extend(server.recv(1 << 16))
+
+        with server, client, Ring(32) as ring:
+            client.setsockopt(socket.SOL_SOCKET, socket.SO_SNDBUF, 4096)
+            reader = threading.Thread(target=drain)
+            reader.start()
+            ring.prep_socket_send_all(1, client.fileno(), payload)
+            ring.submit()
+            assert ring.wait().res == len(payload)
+            reader.join()
+            assert ring.peek() is None
+            assert bytes(received) == payload
+
+    def test_recv_after_peer_close_is_eof(self) -> None:
+        server, client = socket.socketpair()
+        with server, Ring(32) as ring:
+            client.sendall(b"bye")
+            client.close()
+            ring.prep_socket_recv(1, server.fileno(), bytearray(16))
+            ring.prep_socket_recv(2, server.fileno(), bytearray(16))
+            ring.prep_socket_recv(3, UNUSED_FD, bytearray(16))
+            ring.submit()
+            waited = [ring.wait() for _ in range(3)]
+            events = {event.user_data: event for event in waited}
+
+            assert (events[1].bytes_transferred, events[1].is_eof) == (3, False)
+            assert (events[2].bytes_transferred, events[2].is_eof) == (0, True)
+            assert (events[3].bytes_transferred, events[3].is_eof) == (0, False)
+
+    def test_recv_peek_leaves_data_queued(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            client.send(b"peek")
+            buf = bytearray(4)
+            ring.prep_socket_recv(0, server.fileno(), buf, MSG_PEEK)
+            ring.submit()
+            assert ring.wait().res == 4
+            assert server.recv(4) == b"peek"
+
+            with pytest.raises(ValueError, match="MSG_"):
+                ring.prep_socket_recv(1, server.fileno(), buf, 1 << 28)
+
+    def test_socket_flags_are_applied(self) -> None:
+        with Ring(32) as ring:
+            ring.prep_socket(0, AF_INET, SOCK_STREAM, flags=SOCK_CLOEXEC)
+            ring.submit()
+            fd = ring.wait().res
+
+        try:
+            assert fcntl.fcntl(fd, fcntl.F_GETFD) & fcntl.FD_CLOEXEC
+        finally:
+            os.close(fd)
+
+    def test_checked_connect_reports_refused(self) -> None:
+        with socket.socket() as listener:
+            listener.bind(("127.0.0.1", 0))
+            port = listener.getsockname()[1]
+
+        error = SockErrorBuffer()
+        with socket.socket() as client, Ring(32) as ring:
+            ring.prep_socket_connect_checked(
+                0, client.fileno(), SockAddr.v4("127.0.0.1", port), error
+            )
+            ring.submit()
+            connect_event = ring.wait()
+            ring.wait()
+
+        assert connect_event.res == -errno.ECONNREFUSED
+        assert error.error == errno.ECONNREFUSED
+
+    def test_checked_connect_ignores_failed_getsockopt(self) -> None:
+        so_error_tag = 1 << 62
+        # A NOP (with IORING_NOP_INJECT_RESULT) posting -EOPNOTSUPP under the
+        # getsockopt's user_data, as it fails on kernels without socket uring_cmd.
+        sqe = bytearray(64)
+        sqe[24:28] = (-errno.EOPNOTSUPP).to_bytes(4, sys.byteorder, signed=True)
+        sqe[28:32] = (1).to_bytes(4, sys.byteorder)
+        sqe[32:40] = so_error_tag.to_bytes(8, sys.byteorder)
+
+        error = SockErrorBuffer()
+        with (
+            socket.create_server(("127.0.0.1", 0)) as listener,
+            socket.socket() as client,
+            Ring(32) as ring,
+        ):
+            port = listener.getsockname()[1]
+            ring.unsafe_push_raw_sqe(bytes(sqe))
+            ring.prep_socket_connect_checked(
+                0, client.fileno(), SockAddr.v4("127.0.0.1", port), error
+            )
+            events = ring.submit_wait_drain(3)
+
+        assert [event.res for event in events[:2]] == [-errno.EOPNOTSUPP, 0]
+        assert error.error == 0
+
+    def test_uring_cmd_socket_inq(self) -> None:
+        with socket.create_server(("127.0.0.1", 0)) as listener:
+            sender = socket.create_connection(listener.getsockname())
+            receiver, _ = listener.accept()
+        with receiver, sender, Ring(32) as ring:
+            sender.send(b"hello")
+            time.sleep(0.05)
+            ring.prep_uring_cmd(0, receiver.fileno(), SOCKET_URING_OP_SIOCINQ)
+            ring.submit()
+            assert ring.wait().res == 5
+
+            with pytest.raises(ValueError, match="sqe128"):
+                ring.prep_uring_cmd(1, receiver.fileno(), 0, bytes(80))
+
+    def test_uring_cmd_cookie_with_16_byte_payload(self) -> None:
+        with socket.create_server(("127.0.0.1", 0)) as listener:
+            sender = socket.create_connection(listener.getsockname())
+            receiver, _ = listener.accept()
+        with receiver, sender, Ring(32) as ring:
+            ring.prep_uring_cmd(
+                0, receiver.fileno(), SOCKET_URING_OP_SIOCINQ, bytes(16), cookie="cmd"
+            )
+            ring.submit()
+            event = ring.wait()
+            assert (event.res, event.cookie) == (0, "cmd")
+
+    def test_accept_flags_are_applied(self) -> None:
+        with socket.create_server(("127.0.0.1", 0)) as listener, Ring(32) as ring:
+            ring.prep_socket_accept(0, listener.fileno(), flags=SOCK_CLOEXEC)
+            ring.submit()
+            with socket.create_connection(listener.getsockname()):
+                fd = ring.wait().res
+
+        try:
+            assert fcntl.fcntl(fd, fcntl.F_GETFD) & fcntl.FD_CLOEXEC
+        finally:
+            os.close(fd)
+
+    def test_socket_from_accepted_fd(self) -> None:
+        with socket.create_server(("127.0.0.1", 0)) as listener, Ring(32) as ring:
+            ring.prep_socket_accept(0, listener.fileno())
+            ring.submit()
+            with socket.create_connection(listener.getsockname()) as client:
+                with socket_from_fd(ring.wait().res) as conn:
+                    assert conn.family == socket.AF_INET
+                    assert conn.type == socket.SOCK_STREAM
+                    client.sendall(b"ping")
+                    assert conn.recv(4) == b"ping"
+
+    def test_prep_listener_chain(self) -> None:
+        with Ring(32) as ring:
+            tokens = ring.prep_listener(10, SockAddr.v4("127.0.0.1", 0))
+            assert tokens == [10, 11, 12, 13, 14]
+            ring.submit()
+            events = {event.user_data: event for event in ring.submit_wait_drain(5)}
+            assert [events[token].res for token in tokens[:-1]] == [0, 0, 0, 0]
+
+            with socket_from_fd(events[14].res) as listener:
+                assert listener.getsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR)
+                with socket.create_connection(listener.getsockname()):
+                    conn, _ = listener.accept()
+                    conn.close()
+
+    def test_prep_listener_reports_bind_failure(self) -> None:
+        with socket.create_server(("127.0.0.1", 0)) as taken, Ring(32) as ring:
+            addr = SockAddr.v4(*taken.getsockname())
+            tokens = ring.prep_listener(0, addr, reuseaddr=False)
+            events = {event.user_data: event for event in ring.submit_wait_drain(4)}
+            assert events[tokens[1]].res == -errno.EADDRINUSE
+            assert events[tokens[-1]].res == -errno.ECANCELED
+
+    def test_close_file_index_empties_the_slot(self) -> None:
+        left, right = socket.socketpair()
+        with right, Ring(32) as ring:
+            right.setblocking(False)
+            ring.register_files_sparse(4)
+            assert ring.register_files_update(1, [left.fileno()]) == 1
+            ring.prep_close(0, left.detach())
+            ring.submit()
+            assert ring.wait().res == 0
+            with pytest.raises(BlockingIOError):
+                right.recv(1)
+
+            ring.prep_close(1, file_index=1)
+            ring.submit()
+            assert ring.wait().res == 0
+            assert right.recv(1) == b""
+
+            ring.prep_close(2, file_index=1)
+            ring.submit()
+            assert ring.wait().res == -errno.EBADF
+            with pytest.raises(ValueError, match="exactly one"):
+                ring.prep_close(3)
+            with pytest.raises(ValueError, match="file_index 4"):
+                ring.prep_close(3, file_index=4)
+
+    def test_prep_listener_and_file_table_exclude_each_other(self) -> None:
+        addr = SockAddr.v4("127.0.0.1", 0)
+        with Ring(32) as ring:
+            ring.register_files_sparse(4)
+            with pytest.raises(ValueError, match="its own file table"):
+                ring.prep_listener(0, addr)
+        with Ring(32) as ring:
+            ring.prep_listener(0, addr)
+            with pytest.raises(ValueError, match="already registered"):
+                ring.register_files_sparse(4)
+
+    def test_one_sock_addr_for_many_connects(self) -> None:
+        with socket.create_server(("127.0.0.1", 0)) as listener, Ring(32) as ring:
+            addr = SockAddr.v4(*listener.getsockname())
+            clients = [socket.socket() for _ in range(3)]
+            for user_data, client in enumerate(clients):
+                ring.prep_socket_connect(user_data, client.fileno(), addr)
+            del addr
+            ring.submit()
+            assert [ring.wait().res for _ in clients] == [0, 0, 0]
+            for client in clients:
+                client.close()
+
+    def test_connect_timeout_completes_in_time(self) -> None:
+        with (
+            socket.create_server(("127.0.0.1", 0)) as listener,
+            socket.socket() as client,
+            Ring(32) as ring,
+        ):
+            port = listener.getsockname()[1]
+            connect_token, timeout_token = ring.prep_socket_connect_timeout(
+                0, client.fileno(), SockAddr.v4("127.0.0.1", port), sec=5, nsec=0
+            )
+            ring.submit()
+            events = {event.user_data: event for event in (ring.wait(), ring.wait())}
+
+        assert events[connect_token].res == 0
+        assert events[timeout_token].res == -errno.ECANCELED
+
+    def test_wait_and_drain_returns_all_ready(self) -> None:
+        with Ring(32) as ring:
+            for user_data in range(4):
+                ring.prep_nop(user_data)
+            ring.submit()
+
+            events = ring.wait_and_drain()
+            assert sorted(event.user_data for event in events) == [0, 1, 2, 3]
+            assert ring.peek() is None
+
+    def test_sq_pending_counts_unsubmitted_sqes(self) -> None:
+        with Ring(32) as ring:
+            assert ring.sq_pending() == 0
+            ring.prep_nop(0)
+            ring.prep_nop(1)
+            assert ring.sq_pending() == 2
+            assert ring.submit() == 2
+            assert ring.sq_pending() == 0
+
+    def test_wait_flushes_sqes_left_after_a_failed_prep(self) -> None:
+        with Ring(32) as ring:
+            # The kernel stops submitting at an SQE with an unknown opcode.
+            for user_data in (1, 3):
+                sqe = bytearray(64)
+                sqe[0] = 0xFF
+                sqe[32:40] = user_data.to_bytes(8, "little")
+                ring.prep_nop(user_data - 1)
+                ring.unsafe_push_raw_sqe(bytes(sqe))
+            ring.prep_nop(4)
+            assert ring.submit() == 2
+            assert ring.sq_pending() == 3
+
+            assert ring.wait().user_data == 0
+            assert ring.sq_pending() == 0
+            events = {event.user_data: event.res for event in ring.wait_and_drain()}
+            assert events == {1: -errno.EINVAL, 2: 0, 3: -errno.EINVAL, 4: 0}
+
+    def test_for_each_completion_calls_back_per_cqe(self) -> None:
+        with Ring(32) as ring:
+            seen = []
+
+            def on_completion(user_data: int, res: int, flags: int) -> None:
+                seen.append((user_data, res, flags))
+                if user_data == 0:
+                    ring.prep_nop(10)
+                if user_data == 2:
+                    raise KeyError(user_data)
+
+            for user_data in range(4):
+                ring.prep_nop(user_data)
+            ring.submit()
+            with pytest.raises(KeyError):
+                ring.for_each_completion(on_completion)
+            assert seen == [(0, 0, 0), (1, 0, 0), (2, 0, 0)]
+
+            assert ring.for_each_completion(on_completion) == 1
+            assert seen[-1] == (3, 0, 0)
+            ring.submit()
+            assert ring.wait().user_data == 10
+
+    def test_try_submit_reports_what_is_left(self) -> None:
+        with Ring(32) as ring:
+            assert ring.try_submit() == (0, 0)
+            sqe = bytearray(64)
+            sqe[0] = 0xFF
+            ring.prep_nop(0)
+            ring.unsafe_push_raw_sqe(bytes(sqe))
+            ring.prep_nop(1)
+            assert ring.try_submit() == (2, 1)
+            assert ring.try_submit() == (1, 0)
+
+    def test_cq_overflow_reports_backlogged_completions(self) -> None:
+        with Ring(4) as ring:
+            assert ring.cq_overflow() == (0, False)
+            for round in range(3):
+                for i in range(4):
+                    ring.prep_nop(round * 4 + i)
+                ring.submit()
+            assert ring.cq_overflow() == (0, True)
+            seen = {ring.wait().user_data for _ in range(12)}
+            assert seen == set(range(12))
+            assert ring.cq_overflow() == (0, False)
+
+    def test_submit_with_backlogged_completions_raises(self) -> None:
+        with Ring(4) as ring:
+            for round in range(3):
+                for i in range(4):
+                    ring.prep_nop(round * 4 + i)
+                ring.submit()
+            ring.prep_nop(12)
+            with pytest.raises(CompletionQueueFull):
+                ring.submit()
+            assert ring.try_submit() == (0, 1)
+
+            seen = {ring.wait().user_data for _ in range(13)}
+            assert seen == set(range(13))
+
+    def test_submit_wait_drain_waits_for_min_complete(self) -> None:
+        with Ring(32) as ring:
+            ring.prep_nop(1)
+            ring.prep_timeout(2, sec=0, nsec=int(1e7))
+            events = ring.submit_wait_drain(2)
+            assert sorted(event.user_data for event in events) == [1, 2]
+            assert ring.submit_wait_drain(0) == []
+
+    def test_setup_failure_raises_ring_setup_error(self) -> None:
+        with pytest.raises(RingSetupError) as exc_info, Ring(1 << 30):
+            pass
+
+        assert exc_info.value.errno == errno.EINVAL
+        assert isinstance(exc_info.value, RingError)
+        assert isinstance(exc_info.value, OSError)
+
+    def test_wait_for_buffers_other_completions(self) -> None:
+        with Ring(32) as ring:
+            ring.prep_nop(0)
+            ring.prep_nop(1)
+            ring.prep_timeout(2, 0, int(1e7))
+            ring.submit()
+
+            assert ring.wait_for(2).user_data == 2
+            assert ring.peek().user_data == 0
+            assert ring.wait().user_data == 1
+            assert ring.peek() is None
+
+    def test_buffered_completions_count_as_in_flight(self) -> None:
+        with Ring(32) as ring:
+            ring.prep_nop(0)
+            ring.prep_nop(1)
+            ring.submit()
+
+            ring.wait_for(1)
+            assert ring.in_flight == 1
+            assert [event.user_data for event in ring.wait_and_drain()] == [0]
+            assert ring.in_flight == 0
+
+    def test_advance_completions_defers_pin_release(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            client.sendall(b"data")
+            ring.prep_socket_recv(1, server.fileno(), bytearray(16))
+            ring.prep_nop(2)
+            ring.submit()
+            while len(ring.completion_view()) < 2:
+                ring.get_events()
+
+            assert ring.completion_view(1) == [(1, 4, 0)]
+            assert ring.advance_completions(5) == 2
+            assert ring.completion_view() == []
+            assert ring.in_flight == 0
+            assert ring.reconcile_completions() == 2
+            assert ring.peek() is None
+
+    def test_completions_into_fills_rows(self) -> None:
+        with Ring(32) as ring:
+            ring.prep_nop(1)
+            ring.prep_cancel(2, 99)
+            ring.prep_nop(3)
+            ring.submit()
+
+            out = array.array("q", [0] * 6)
+            assert ring.completions_into(out) == 2
+            assert out.tolist() == [1, 0, 0, 2, -errno.ENOENT, 0]
+            assert ring.completions_into(out) == 1
+            assert out[:3].tolist() == [3, 0, 0]
+            assert ring.completions_into(out) == 0
+
+    def test_completions_into_rejects_partial_rows(self) -> None:
+        with Ring(32) as ring, pytest.raises(ValueError, match="whole number"):
+            ring.completions_into(array.array("q", [0] * 4))
+
+    def test_wait_for_times_out(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            ring.prep_socket_recv(0, server.fileno(), bytearray(16))
+            ring.submit()
+            assert ring.wait_for(0, timeout=0.05) is None
+
+    def test_default_wait_timeout(self) -> None:
+        with Ring(32) as ring:
+            ring.set_default_wait_timeout(0, int(5e7))
+            ring.prep_timeout(0, sec=10, nsec=0)
+            with pytest.raises(TimeoutError):
+                ring.wait()
+            ring.prep_nop(1)
+            assert ring.wait().user_data == 1
+
+            ring.set_default_wait_timeout(None)
+            ring.prep_cancel(2, 0)
+            assert {ring.wait().user_data for _ in range(2)} == {0, 2}
+
+    def test_shutdown_cancels_multishot_and_closes(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            ring.register_buf_ring(group_id=1, entries=8, buf_size=16)
+            ring.prep_socket_recv_multishot(0, server.fileno(), group_id=1)
+            ring.prep_nop(1)
+            ring.submit()
+            assert ring.in_flight == 2
+
+            events = ring.shutdown(timeout=5, cancel=True)
+            assert sorted(event.user_data for event in events) == [0, 1]
+            assert ring.in_flight == 0
+            assert not ring.is_open
+
+    def test_shutdown_timeout_keeps_ring_open(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            ring.prep_socket_recv(0, server.fileno(), bytearray(16))
+            ring.submit()
+
+            assert ring.shutdown(timeout=0.05) == []
+            assert ring.is_open
+            assert ring.in_flight == 1
+            with pytest.raises(RingError):
+                ring.prep_nop(1)
+
+            client.send(b"done")
+            (event,) = ring.shutdown()
+            assert event.res == 4
+            assert not ring.is_open
+
+    def test_register_iowq_max_workers_returns_previous(self) -> None:
+        with Ring(32) as ring:
+            ring.register_iowq_max_workers(4, 0)
+            assert ring.register_iowq_max_workers(2, 0)[0] == 4
+
+    def test_iowq_max_workers_reads_without_changing(self) -> None:
+        with Ring(32) as ring:
+            _, unbounded = ring.iowq_max_workers()
+            ring.register_iowq_max_workers(3, 0)
+            assert ring.iowq_max_workers() == (3, unbounded)
+            assert ring.iowq_max_workers() == (3, unbounded)
+
+    def test_register_iowq_aff(self) -> None:
+        with Ring(32) as ring:
+            ring.register_iowq_aff(os.sched_getaffinity(0))
+            ring.unregister_iowq_aff()
+            with pytest.raises(ValueError, match="out of range"):
+                ring.register_iowq_aff([1 << 20])
+
+    def test_buffer_pool_round_robin_read_fixed(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            pool = ring.register_buffer_pool(count=2, buf_size=16)
+            client.send(b"hello")
+
+            index = pool.acquire(0)
+            ring.prep_read_fixed(0, server.fileno(), index, 16, 0)
+            ring.submit()
+            event = ring.wait()
+
+            assert pool.read(index, event.res) == b"hello"
+            assert pool.available == 2
+            assert pool.acquire(1) != index
+
+    def test_write_fixed_sends_a_slice_of_the_buffer(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            pool = ring.register_buffer_pool(count=1, buf_size=16)
+            index = pool.acquire(0)
+            pool.write(index, b"headbodytail")
+
+            ring.prep_write_fixed(0, client.fileno(), index, 4, 0, buf_offset=4)
+            ring.submit()
+            assert ring.wait().res == 4
+            assert server.recv(16) == b"body"
+
+            with pytest.raises(ValueError, match="offset 8"):
+                ring.prep_write_fixed(1, client.fileno(), index, 12, 0, buf_offset=8)
+
+    def test_cookie_comes_back_on_every_completion(self) -> None:
+        class Token:
+            pass
+
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            token = Token()
+            released = weakref.ref(token)
+            ring.prep_poll_add(1, server.fileno(), POLLIN, multishot=True, cookie=token)
+            ring.prep_nop(2, cookie="nop")
+            ring.prep_nop(3)
+            events = {event.user_data: event for event in ring.submit_wait_drain(2)}
+            assert events[2].cookie == "nop"
+            assert events[3].cookie is None
+
+            client.send(b"x")
+            assert ring.wait().cookie is token
+            ring.prep_cancel(4, 1)
+            events = {event.user_data: event for event in ring.submit_wait_drain(2)}
+            assert events[1].cookie is token
+            assert not events[1].more
+            del token, events
+            assert released() is None
+
+    def test_failed_prep_keeps_live_cookie(self) -> None:
+        with Ring(32) as ring:
+            ring.prep_timeout(1, sec=0, nsec=int(1e6), cookie="live")
+            buf = bytearray(4)
+            with pytest.raises(ValueError, match="buf_offset"):
+                ring.prep_read(1, UNUSED_FD, buf, 4, 0, buf_offset=8, cookie="x")
+            ring.submit()
+            assert ring.wait().cookie == "live"
+
+    def test_repr_and_len_show_state(self) -> None:
+        ring = Ring(8)
+        assert repr(ring) == "Ring(depth=8, open=False)"
+        with ring:
+            ring.prep_nop(0)
+            assert len(ring) == 1
+            assert repr(ring) == "Ring(depth=8, open=True, in_flight=1, sq_space=7)"
+            ring.submit()
+            ring.wait()
+            assert len(ring) == 0
+            assert ring
+
+    def test_reserve_keeps_pinned_ops_working(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(8) as ring:
+            ring.reserve(1024)
+            for user_data in range(4):
+                ring.prep_write(user_data, client.fileno(), b"ab", -1)
+            assert [event.res for event in ring.submit_wait_drain(4)] == [2] * 4
+            assert server.recv(16) == b"ab" * 4
+
+    def test_clamp_caps_oversized_depth(self) -> None:
+        with pytest.raises(RingSetupError):
+            Ring(1 << 20).__enter__()
+        with Ring(1 << 20, clamp=True) as ring:
+            assert 0 < ring.sq_entries < 1 << 20
+
+    def test_ring_fd_is_close_on_exec(self) -> None:
+        with Ring(32) as ring:
+            assert ring.close_on_exec
+
+    def test_zero_depth_is_rejected(self) -> None:
+        with pytest.raises(ValueError, match="at least 1"):
+            Ring(0)
+
+    def test_full_submission_queue_raises_submission_queue_full(self) -> None:
+        with Ring(2) as ring:
+            ring.prep_nop(0)
+            ring.prep_nop(1)
+            with pytest.raises(SubmissionQueueFull):
+                ring.prep_nop(2)
+
+    def test_auto_submit_on_full_submission_queue(self) -> None:
+        with Ring(2, auto_submit=True) as ring:
+            for user_data in range(5):
+                ring.prep_nop(user_data)
+            ring.submit()
+            assert sorted(ring.wait().user_data for _ in range(5)) == list(range(5))
+
+    def test_signal_handler_interrupts_wait(self) -> None:
+        class Interrupted(Exception):  # noqa: N818
+            pass
+
+        def raise_interrupted(signum: int, frame: object) -> None:
+            raise Interrupted
+
+        previous = signal.signal(signal.SIGALRM, raise_interrupted)
+        try:
+            with Ring(32) as ring:
+                ring.prep_timeout(0, 5, 0)
+                signal.setitimer(signal.ITIMER_REAL, 0.05)
+                with pytest.raises(Interrupted):
+                    ring.wait()
+
+                signal.signal(signal.SIGALRM, lambda signum, frame: None)
+                ring.prep_timeout(1, 0, int(2e8))
+                signal.setitimer(signal.ITIMER_REAL, 0.05)
+                assert ring.wait_for(1).user_data == 1
+        finally:
+            signal.setitimer(signal.ITIMER_REAL, 0)
+            signal.signal(signal.SIGALRM, previous)
+
+    def test_unopened_ring_raises_ring_not_initialized(self) -> None:
+        with pytest.raises(RingNotInitialized):
+            Ring(2).submit()
+
+    def test_multishot_poll_rearms(self) -> None:
+        read_fd, write_fd = os.pipe()
+        try:
+            with Ring(32) as ring:
+                ring.prep_poll_add(1, read_fd, POLLIN, multishot=True)
+                ring.submit()
+                for _ in range(2):
+                    os.write(write_fd, b"x")
+                    event = ring.wait()
+                    assert event.res & POLLIN
+                    assert event.more
+                    os.read(read_fd, 1)
+
+                ring.prep_cancel(2, 1)
+                ring.submit()
+                waited = (ring.wait(), ring.wait())
+                events = {event.user_data: event for event in waited}
+                assert events[1].res == -errno.ECANCELED
+                assert not events[1].more
+                assert ring.in_flight == 0
+        finally:
+            os.close(read_fd)
+            os.close(write_fd)
+
+    def test_poll_ring_fires_when_child_has_completions(self) -> None:
+        with Ring(32) as parent, Ring(32) as child:
+            parent.prep_poll_ring(1, child)
+            parent.submit()
+            assert parent.peek() is None
+
+            child.prep_nop(7)
+            child.submit()
+            event = parent.wait()
+            assert event.res & POLLIN
+            assert not event.more
+
+            parent.prep_poll_ring(2, child)
+            assert parent.wait().user_data == 2
+            assert child.peek().user_data == 7
+
+            with pytest.raises(ValueError, match="itself"):
+                parent.prep_poll_ring(3, parent)
+
+    def test_poll_update_switches_mask_and_user_data(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            ring.prep_poll_add(1, server.fileno(), POLLIN, multishot=True)
+            ring.submit()
+            ring.prep_poll_update(2, 1, POLLOUT, new_user_data=3, multishot=True)
+            ring.submit()
+
+            waited = (ring.wait(), ring.wait())
+            events = {event.user_data: event for event in waited}
+            assert events[2].res == 0
+            assert events[3].res & POLLOUT
+            assert events[3].more
+
+            ring.prep_poll_update(4, 99, POLLIN)
+            ring.submit()
+            assert ring.wait_for(4).res == -errno.ENOENT
+
+            with pytest.raises(ValueError, match="new_mask"):
+                ring.prep_poll_update(5, 3, new_user_data=6, multishot=True)
+            assert ring.sq_pending() == 0
+
+    def test_pidfd_wait_then_reap(self) -> None:
+        child = subprocess.Popen(["true"])  # noqa: S607
+        pidfd = os.pidfd_open(child.pid)
+        try:
+            with Ring(32) as ring:
+                ring.prep_pidfd_wait(0, pidfd)
+                ring.submit()
+                assert ring.wait().res & POLLIN
+
+            result = os.waitid(os.P_PIDFD, pidfd, os.WEXITED)
+            assert result is not None
+            assert result.si_status == 0
+        finally:
+            os.close(pidfd)
+
+    def test_recv_timeout_cancels_stalled_recv(self) -> None:
+        server, client = socket.socketpair()
+        with server, client, Ring(32) as ring:
+            recv_token, timeout_token = ring.prep_socket_recv_timeout(
+                1, server.fileno(), bytearray(16), sec=0, nsec=int(1e7)
+            )
+            ring.submit()
+
+            events = {event.user_data: event for event in (ring.wait(), ring.wait())}
+            assert events[recv_token].res == -errno.ECANCELED
+            assert events[timeout_token].res == -errno.ETIME
+
+    def test_get_events_runs_deferred_completions(self) -> None:
+        with Ring(32, defer_taskrun=True) as ring:
+            ring.prep_timeout(1, sec=0, nsec=int(1e6))
+            ring.submit()
+            time.sleep(0.05)
+            assert ring.peek(sync=False) is None
+            assert ring.get_events() == 1
+            assert ring.peek(sync=False).user_data == 1
+
+    def test_peek_syncs_deferred_completions(self) -> None:
+        with Ring(32, defer_taskrun=True) as ring:
+            ring.prep_timeout(1, sec=0, nsec=int(1e6))
+            ring.submit()
+            time.sleep(0.05)
+            event = ring.peek()
+            assert event is not None
+            assert event.user_data == 1
+
+    def test_peek_under_coop_taskrun(self) -> None:
+        with Ring(32, coop_taskrun=True) as ring:
+            ring.prep_timeout(1, sec=0, nsec=int(1e6))
+            ring.submit()
+            time.sleep(0.05)
+            event = ring.peek()
+            assert event is not None
+            assert event.user_data == 1
+
+    def test_submit_nonblocking_under_sqpoll(self) -> None:
+        with Ring(32, sqpoll_idle_ms=100) as ring:
+            ring.prep_nop(user_data=1)
+            ring.submit_nonblocking()
+            assert ring.wait().user_data == 1
+
+    def test_cancel_all_by_opcode(self) -> None:
+        with Ring(32) as ring:
+            ring.prep_timeout(1, sec=10, nsec=0)
+            ring.prep_timeout(2, sec=10, nsec=0)
+            ring.submit()
+
+            ring.prep_cancel(3, 0, IORING_ASYNC_CANCEL_ALL, op_code=IORING_OP_TIMEOUT)
+            ring.submit()
+
+            waited = [ring.wait() for _ in range(3)]
+            events = {event.user_data: event for event in waited}
+            assert events[1].res == -errno.ECANCELED
+            assert events[2].res == -errno.ECANCELED
+            assert events[3].res == 2
+
+    def test_cancel_without_match(self) -> None:
+        with Ring(32) as ring:
+            ring.prep_cancel(1, 99)
+            ring.prep_cancel(2, 0, IORING_ASYNC_CANCEL_ALL, op_code=IORING_OP_TIMEOUT)
+            ring.submit()
+            events = {event.user_data: event for event in (ring.wait(), ring.wait())}
+            assert events[1].res == -errno.ENOENT
+            assert events[2].res == 0
+
+    def test_datagram_truncation_is_reported(self) -> None:
+        receiver, sender = socket.socketpair(socket.AF_UNIX, socket.SOCK_DGRAM)
+        with receiver, sender, Ring(32) as ring:
+            sender.send(b"x" * 32)
+            sender.send(b"y" * 32)
+            sender.send(b"z" * 32)
+            header = RecvMsgHeader()
+            ring.prep_recvmsg(1, receiver.fileno(), [bytearray(8)], header)
+            ring.prep_socket_recv(2, receiver.fileno(), bytearray(8), MSG_TRUNC)
+            real_len_header = RecvMsgHeader()
+            buf = bytearray(8)
+            ring.prep_recvmsg(3, receiver.fileno(), [buf], real_len_header, MSG_TRUNC)
+            events = {event.user_data: event for event in ring.submit_wait_drain(3)}
+
+            assert events[1].res == 8
+            assert header.msg_flags & MSG_TRUNC
+            assert events[2].res == 32
+            assert events[3].res == 32
+            assert buf == b"z" * 8
+
+    def test_sendmsg_zc_posts_send_then_notification(self) -> None:
+        receiver = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+        sender = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+        with receiver, sender, Ring(32) as ring:
+            receiver.bind(("127.0.0.1", 0))
+            addr = SockAddr.v4(*receiver.getsockname())
+            ring.prep_sendmsg_zc(0, sender.fileno(), [b"hello ", b"world"], addr)
+            ring.submit()
+
+            sent, notification = ring.wait(), ring.wait()
+            assert (sent.res, sent.more, sent.is_notification) == (11, True, False)
+            assert (notification.more, notification.is_notification) == (False, True)
+            assert ring.in_flight == 0
+            assert receiver.recv(64) == b"hello world"
+
+    def test_recvmsg_scatters_and_reports_source(self) -> None:
+        receiver = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+        sender = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+        with receiver, sender, Ring(32) as ring:
+            receiver.bind(("127.0.0.1", 0))
+            sender.bind(("127.0.0.1", 0))
+            sender.sendto(b"hello world", receiver.getsockname())
+
+            head, tail = bytearray(4), bytearray(16)
+            header = RecvMsgHeader()
+            ring.prep_recvmsg(0, receiver.fileno(), [head, tail], header)
+            ring.submit()
+
+            assert ring.wait().res == len(b"hello world")
+            assert bytes(head) + bytes(tail[:7]) == b"hello world"
+            assert header.address == sender.getsockname()
+            assert header.msg_flags == 0
+
+    def test_recvmsg_header_cannot_be_reused_in_flight(self) -> None:
+        receiver = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+        sender = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+        with receiver, sender, Ring(32) as ring:
+            receiver.bind(("127.0.0.1", 0))
+            header, buf = RecvMsgHeader(), bytearray(8)
+            ring.prep_recvmsg(0, receiver.fileno(), [buf], header)
+            ring.submit()
+            with pytest.raises(ValueError, match="in-flight"):
+                ring.prep_recvmsg(1, receiver.fileno(), [buf], header)
+
+            sender.sendto(b"once", receiver.getsockname())
+            assert ring.wait().res == 4
+            sender.sendto(b"twice", receiver.getsockname())
+            ring.prep_recvmsg(2, receiver.fileno(), [buf], header)
+            ring.submit()
+            assert ring.wait().res == 5